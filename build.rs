@@ -0,0 +1,20 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(ossl300)");
+    println!("cargo:rustc-check-cfg=cfg(ossl320)");
+
+    // Set by openssl-sys, which has `links = "openssl"`.
+    if env::var("DEP_OPENSSL_LIBRESSL").is_ok() {
+        return;
+    }
+    if let Ok(version) = env::var("DEP_OPENSSL_VERSION_NUMBER") {
+        let version = u64::from_str_radix(&version, 16).unwrap();
+        if version >= 0x3000_0000 {
+            println!("cargo:rustc-cfg=ossl300");
+        }
+        if version >= 0x3020_0000 {
+            println!("cargo:rustc-cfg=ossl320");
+        }
+    }
+}
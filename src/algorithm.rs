@@ -0,0 +1,272 @@
+//! Enum of all supported JWS algorithms.
+
+use std::{fmt, str::FromStr};
+
+use crate::{ecdsa::EcdsaAlgorithm, hmac::HmacAlgorithm, rsa::RsaAlgorithm, Error, Result};
+
+/// A JWS algorithm (the `alg` header parameter).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    HS256,
+    HS384,
+    HS512,
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES256K,
+    ES384,
+    ES512,
+    EdDSA,
+}
+
+/// Groups of algorithms that share a signature scheme.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlgorithmFamily {
+    /// `HS*`.
+    Hmac,
+    /// `RS*`, RSASSA-PKCS1-v1_5.
+    RsaPkcs1,
+    /// `PS*`, RSASSA-PSS.
+    RsaPss,
+    /// `ES*`.
+    Ecdsa,
+    /// `EdDSA`.
+    EdDsa,
+}
+
+impl AlgorithmFamily {
+    /// Whether signing and verification use different keys, i.e. anything
+    /// but HMAC.
+    #[inline]
+    pub fn is_asymmetric(self) -> bool {
+        self != AlgorithmFamily::Hmac
+    }
+}
+
+impl Algorithm {
+    /// All supported algorithms.
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::HS256,
+        Algorithm::HS384,
+        Algorithm::HS512,
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+        Algorithm::PS384,
+        Algorithm::PS512,
+        Algorithm::ES256,
+        Algorithm::ES256K,
+        Algorithm::ES384,
+        Algorithm::ES512,
+        Algorithm::EdDSA,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        use Algorithm::*;
+        match self {
+            HS256 => "HS256",
+            HS384 => "HS384",
+            HS512 => "HS512",
+            RS256 => "RS256",
+            RS384 => "RS384",
+            RS512 => "RS512",
+            PS256 => "PS256",
+            PS384 => "PS384",
+            PS512 => "PS512",
+            ES256 => "ES256",
+            ES256K => "ES256K",
+            ES384 => "ES384",
+            ES512 => "ES512",
+            EdDSA => "EdDSA",
+        }
+    }
+
+    pub fn family(self) -> AlgorithmFamily {
+        use Algorithm::*;
+        match self {
+            HS256 | HS384 | HS512 => AlgorithmFamily::Hmac,
+            RS256 | RS384 | RS512 => AlgorithmFamily::RsaPkcs1,
+            PS256 | PS384 | PS512 => AlgorithmFamily::RsaPss,
+            ES256 | ES256K | ES384 | ES512 => AlgorithmFamily::Ecdsa,
+            EdDSA => AlgorithmFamily::EdDsa,
+        }
+    }
+
+    #[inline]
+    pub fn is_asymmetric(self) -> bool {
+        self.family().is_asymmetric()
+    }
+
+    /// The JWK key type (`kty`) of keys used with this algorithm.
+    pub fn key_type(self) -> &'static str {
+        use Algorithm::*;
+        match self {
+            HS256 | HS384 | HS512 => "oct",
+            RS256 | RS384 | RS512 | PS256 | PS384 | PS512 => "RSA",
+            ES256 | ES256K | ES384 | ES512 => "EC",
+            EdDSA => "OKP",
+        }
+    }
+
+    /// Returns [`Error::AlgorithmKeyMismatch`] if `alg` is a known algorithm
+    /// that is not used with keys of type `kty`, e.g. `HS256` with an RSA key.
+    pub(crate) fn check_key_type(alg: &str, kty: &str) -> Result<()> {
+        match Self::from_name(alg) {
+            Ok(alg) if alg.key_type() != kty => Err(Error::AlgorithmKeyMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Exact, case sensitive match of the algorithm name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|alg| alg.as_str() == name)
+            .ok_or(Error::UnsupportedOrInvalidKey)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_name(s)
+    }
+}
+
+impl From<HmacAlgorithm> for Algorithm {
+    fn from(alg: HmacAlgorithm) -> Self {
+        match alg {
+            HmacAlgorithm::HS256 => Algorithm::HS256,
+            HmacAlgorithm::HS384 => Algorithm::HS384,
+            HmacAlgorithm::HS512 => Algorithm::HS512,
+        }
+    }
+}
+
+impl From<RsaAlgorithm> for Algorithm {
+    fn from(alg: RsaAlgorithm) -> Self {
+        match alg {
+            RsaAlgorithm::RS256 => Algorithm::RS256,
+            RsaAlgorithm::RS384 => Algorithm::RS384,
+            RsaAlgorithm::RS512 => Algorithm::RS512,
+            RsaAlgorithm::PS256 => Algorithm::PS256,
+            RsaAlgorithm::PS384 => Algorithm::PS384,
+            RsaAlgorithm::PS512 => Algorithm::PS512,
+        }
+    }
+}
+
+impl From<EcdsaAlgorithm> for Algorithm {
+    fn from(alg: EcdsaAlgorithm) -> Self {
+        match alg {
+            EcdsaAlgorithm::ES256 => Algorithm::ES256,
+            EcdsaAlgorithm::ES256K => Algorithm::ES256K,
+            EcdsaAlgorithm::ES384 => Algorithm::ES384,
+            EcdsaAlgorithm::ES512 => Algorithm::ES512,
+        }
+    }
+}
+
+macro_rules! impl_display_from_str {
+    ($($alg:ty),*) => {$(
+        impl fmt::Display for $alg {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.name().fmt(f)
+            }
+        }
+
+        impl FromStr for $alg {
+            type Err = Error;
+
+            #[inline]
+            fn from_str(s: &str) -> Result<Self> {
+                Self::from_name(s)
+            }
+        }
+    )*};
+}
+
+impl_display_from_str!(HmacAlgorithm, RsaAlgorithm, EcdsaAlgorithm);
+
+macro_rules! impl_family {
+    ($($alg:ty),*) => {$(
+        impl $alg {
+            #[inline]
+            pub fn family(self) -> AlgorithmFamily {
+                Algorithm::from(self).family()
+            }
+
+            #[inline]
+            pub fn is_asymmetric(self) -> bool {
+                Algorithm::from(self).is_asymmetric()
+            }
+        }
+    )*};
+}
+
+impl_family!(HmacAlgorithm, RsaAlgorithm, EcdsaAlgorithm);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() -> Result<()> {
+        for &alg in Algorithm::ALL {
+            assert_eq!(alg.as_str().parse::<Algorithm>()?, alg);
+            assert_eq!(alg.to_string(), alg.as_str());
+        }
+        assert!("Rs256".parse::<Algorithm>().is_err());
+        assert_eq!(Algorithm::from(RsaAlgorithm::PS384), Algorithm::PS384);
+        assert_eq!(Algorithm::from(EcdsaAlgorithm::ES256K), Algorithm::ES256K);
+        assert_eq!(Algorithm::from(HmacAlgorithm::HS512), Algorithm::HS512);
+
+        assert_eq!("RS256".parse::<RsaAlgorithm>()?, RsaAlgorithm::RS256);
+        assert_eq!(RsaAlgorithm::PS512.to_string(), "PS512");
+        assert_eq!("ES256K".parse::<EcdsaAlgorithm>()?, EcdsaAlgorithm::ES256K);
+        assert_eq!(EcdsaAlgorithm::ES384.to_string(), "ES384");
+        assert_eq!("HS384".parse::<HmacAlgorithm>()?, HmacAlgorithm::HS384);
+        assert_eq!(format!("{:>6}", HmacAlgorithm::HS256), " HS256");
+        assert!("ES256".parse::<RsaAlgorithm>().is_err());
+
+        // Lenient for config, but not for headers.
+        assert_eq!(RsaAlgorithm::from_name(" rs256\n")?, RsaAlgorithm::RS256);
+        assert_eq!(EcdsaAlgorithm::from_name("Es256k")?, EcdsaAlgorithm::ES256K);
+        assert_eq!("hs384 ".parse::<HmacAlgorithm>()?, HmacAlgorithm::HS384);
+        assert!(RsaAlgorithm::from_name("rs 256").is_err());
+        assert!(RsaAlgorithm::from_exact_name("rs256").is_err());
+        assert!(Algorithm::from_name("rs256").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn families() {
+        for &alg in Algorithm::ALL {
+            assert_eq!(alg.is_asymmetric(), alg.key_type() != "oct");
+        }
+        assert_eq!(Algorithm::PS256.family(), AlgorithmFamily::RsaPss);
+        assert_eq!(Algorithm::RS512.family(), AlgorithmFamily::RsaPkcs1);
+        assert_eq!(Algorithm::EdDSA.family(), AlgorithmFamily::EdDsa);
+        assert_eq!(RsaAlgorithm::PS384.family(), AlgorithmFamily::RsaPss);
+        assert_eq!(EcdsaAlgorithm::ES256K.family(), AlgorithmFamily::Ecdsa);
+        assert_eq!(HmacAlgorithm::HS256.family(), AlgorithmFamily::Hmac);
+        assert!(!HmacAlgorithm::HS512.is_asymmetric());
+        assert!(RsaAlgorithm::RS256.is_asymmetric());
+    }
+}
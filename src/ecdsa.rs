@@ -1,19 +1,25 @@
 use base64::Engine as _;
 use foreign_types::ForeignTypeRef;
 use openssl::{
-    bn::{BigNum, BigNumContext},
-    ec::{EcGroup, EcKey},
+    bn::{BigNum, BigNumContext, BigNumRef},
+    ec::{EcGroup, EcGroupRef, EcKey, EcPoint},
     ecdsa::EcdsaSig,
-    hash::{hash, MessageDigest},
+    hash::{hash, DigestBytes, Hasher, MessageDigest},
     nid::Nid,
     pkey::{HasPublic, PKey, PKeyRef, Private, Public},
 };
+#[cfg(ossl320)]
+use openssl::{
+    md::Md,
+    pkey_ctx::{NonceType, PkeyCtx},
+};
 use openssl_sys::BN_bn2bin;
 use smallvec::{smallvec, SmallVec};
+use std::{cmp::Ordering, io::Read};
 
 use crate::{
-    jwk::Jwk, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey, VerificationKey,
-    URL_SAFE_TRAILING_BITS,
+    jwk::Jwk, Algorithm, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey,
+    VerificationKey, URL_SAFE_TRAILING_BITS,
 };
 
 #[non_exhaustive]
@@ -68,6 +74,25 @@ impl EcdsaAlgorithm {
         }
     }
 
+    /// Parse a name from e.g. a config file: surrounding whitespace is
+    /// ignored and the match is case-insensitive, so ` es256 ` is `ES256`.
+    ///
+    /// Header `alg` values are always matched exactly.
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::from_exact_name(&name.trim().to_ascii_uppercase())
+    }
+
+    pub(crate) fn from_exact_name(name: &str) -> Result<Self> {
+        use EcdsaAlgorithm::*;
+        Ok(match name {
+            "ES256" => ES256,
+            "ES256K" => ES256K,
+            "ES384" => ES384,
+            "ES512" => ES512,
+            _ => return Err(Error::UnsupportedOrInvalidKey),
+        })
+    }
+
     #[inline]
     pub fn curve_name(self) -> &'static str {
         use EcdsaAlgorithm::*;
@@ -91,6 +116,16 @@ impl EcdsaAlgorithm {
         })
     }
 
+    // Size of the curve order in bits.
+    pub(crate) fn bits(self) -> u32 {
+        use EcdsaAlgorithm::*;
+        match self {
+            ES256 | ES256K => 256,
+            ES384 => 384,
+            ES512 => 521,
+        }
+    }
+
     // Signature length. Also == 2 * r == 2 * s == 2 * x == 2 * y.
     fn len(self) -> usize {
         use EcdsaAlgorithm::*;
@@ -102,10 +137,41 @@ impl EcdsaAlgorithm {
     }
 }
 
-#[derive(Debug, Clone)]
+/// ECDSA Private Key.
+///
+/// Set `low_s` to `true` to normalize signatures into the canonical low-S form
+/// (`s <= n / 2`) when signing, and to reject high-S signatures when verifying.
+/// This is required by some verifiers, e.g. in blockchain/DID systems, that
+/// consider high-S signatures malleable.
+///
+/// Set `deterministic` to `true` to derive the nonce from the key and the
+/// message ([RFC 6979](https://www.rfc-editor.org/rfc/rfc6979)) instead of
+/// using a random nonce, so that signing the same message twice gives the same
+/// signature. The signature format is the same either way. This needs OpenSSL
+/// 3.2 or later, signing fails with [`Error::UnsupportedOrInvalidKey`] on older
+/// versions.
+///
+/// Set `tolerate_short_sig` to `true` to accept signatures whose `r` or `s`
+/// lost leading zero bytes when verifying, see [`EcdsaPublicKey`].
+#[derive(Clone)]
 pub struct EcdsaPrivateKey {
     private_key: PKey<Private>,
     algorithm: EcdsaAlgorithm,
+    pub low_s: bool,
+    pub deterministic: bool,
+    pub tolerate_short_sig: bool,
+}
+
+impl std::fmt::Debug for EcdsaPrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EcdsaPrivateKey")
+            .field("algorithm", &self.algorithm)
+            .field("low_s", &self.low_s)
+            .field("deterministic", &self.deterministic)
+            .field("tolerate_short_sig", &self.tolerate_short_sig)
+            .field("private_key", &format_args!("<redacted>"))
+            .finish()
+    }
 }
 
 impl EcdsaPrivateKey {
@@ -114,6 +180,38 @@ impl EcdsaPrivateKey {
         Ok(Self {
             private_key: PKey::from_ec_key(ec_key)?,
             algorithm,
+            low_s: false,
+            deterministic: false,
+            tolerate_short_sig: false,
+        })
+    }
+
+    /// Deterministically derive a key from `seed`, e.g. for reproducible test
+    /// fixtures. The private scalar is `seed mod n`, which must not be zero.
+    ///
+    /// Use [`generate`](Self::generate) for real keys.
+    pub fn from_seed(algorithm: EcdsaAlgorithm, seed: &[u8; 32]) -> Result<Self> {
+        let group = EcGroup::from_curve_name(algorithm.curve())?;
+        let mut ctx = BigNumContext::new()?;
+        let mut order = BigNum::new()?;
+        group.order(&mut order, &mut ctx)?;
+        let mut d = BigNum::new()?;
+        d.nnmod(BigNum::from_slice(seed)?.as_ref(), &order, &mut ctx)?;
+        if d.num_bits() == 0 {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+        let mut public_key = EcPoint::new(&group)?;
+        // `mul_generator2` needs a newer openssl crate than we require. `ctx`
+        // is local, so the deprecated version is fine here.
+        #[allow(deprecated)]
+        public_key.mul_generator(&group, &d, &ctx)?;
+        let ec_key = EcKey::from_private_components(&group, &d, &public_key)?;
+        Ok(Self {
+            private_key: PKey::from_ec_key(ec_key)?,
+            algorithm,
+            low_s: false,
+            deterministic: false,
+            tolerate_short_sig: false,
         })
     }
 
@@ -129,6 +227,9 @@ impl EcdsaPrivateKey {
         Ok(Self {
             private_key: pk,
             algorithm,
+            low_s: false,
+            deterministic: false,
+            tolerate_short_sig: false,
         })
     }
 
@@ -153,6 +254,9 @@ impl EcdsaPrivateKey {
         Ok(Self {
             private_key: PKey::from_ec_key(k)?,
             algorithm,
+            low_s: false,
+            deterministic: false,
+            tolerate_short_sig: false,
         })
     }
 
@@ -161,12 +265,24 @@ impl EcdsaPrivateKey {
         Self::from_pkey(pk)
     }
 
+    #[inline]
+    pub fn algorithm(&self) -> EcdsaAlgorithm {
+        self.algorithm
+    }
+
     pub fn private_key_to_pem_pkcs8(&self) -> Result<String> {
         Ok(String::from_utf8(
             self.private_key.private_key_to_pem_pkcs8()?,
         )?)
     }
 
+    /// BEGIN EC PRIVATE KEY
+    pub fn private_key_to_pem_sec1(&self) -> Result<String> {
+        Ok(String::from_utf8(
+            self.private_key.ec_key()?.private_key_to_pem()?,
+        )?)
+    }
+
     pub fn public_key_to_pem(&self) -> Result<String> {
         Ok(String::from_utf8(self.private_key.public_key_to_pem()?)?)
     }
@@ -185,8 +301,23 @@ impl EcdsaPrivateKey {
         Ok((x, y))
     }
 
+    /// Private scalar. Always padded to the full size.
     pub fn d(&self) -> Result<Vec<u8>> {
-        Ok(self.private_key.ec_key()?.private_key().to_vec())
+        Ok(self
+            .private_key
+            .ec_key()?
+            .private_key()
+            .to_vec_padded(self.algorithm.len() as i32 / 2)?)
+    }
+
+    /// Convert to a JWK, including the private key material only if
+    /// `include_private` is `true`.
+    pub fn to_jwk(&self, include_private: bool) -> Result<Jwk> {
+        if include_private {
+            self.private_key_to_jwk()
+        } else {
+            self.public_key_to_jwk()
+        }
     }
 }
 
@@ -241,12 +372,32 @@ fn test_pad_left() {
     assert_eq!(v, [0, 0, 0, 0, 0, 5, 6, 7]);
 }
 
-#[derive(Debug)]
+/// ECDSA Public Key.
+///
+/// Set `low_s` to `true` to reject signatures that are not in the canonical
+/// low-S form (`s <= n / 2`).
+///
+/// Set `tolerate_short_sig` to `true` to also accept signatures that are
+/// shorter than the fixed length because some client stripped leading zero
+/// bytes off `r` or `s`. Such signatures violate the JWS spec, so they are
+/// rejected by default.
+#[derive(Debug, Clone)]
 pub struct EcdsaPublicKey {
     public_key: PKey<Public>,
     algorithm: EcdsaAlgorithm,
+    pub low_s: bool,
+    pub tolerate_short_sig: bool,
+}
+
+/// Compares the key material only.
+impl PartialEq for EcdsaPublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_key.public_eq(&other.public_key)
+    }
 }
 
+impl Eq for EcdsaPublicKey {}
+
 impl EcdsaPublicKey {
     pub(crate) fn from_pkey(pkey: PKey<Public>) -> Result<Self> {
         pkey.ec_key()?.check_key()?;
@@ -261,6 +412,8 @@ impl EcdsaPublicKey {
         Ok(Self {
             public_key: pkey,
             algorithm,
+            low_s: false,
+            tolerate_short_sig: false,
         })
     }
 
@@ -287,6 +440,11 @@ impl EcdsaPublicKey {
         Ok((x, y))
     }
 
+    #[inline]
+    pub fn algorithm(&self) -> EcdsaAlgorithm {
+        self.algorithm
+    }
+
     pub fn from_coordinates(x: &[u8], y: &[u8], algorithm: EcdsaAlgorithm) -> Result<Self> {
         let k = EcKey::from_public_key_affine_coordinates(
             EcGroup::from_curve_name(algorithm.curve())?.as_ref(),
@@ -297,6 +455,8 @@ impl EcdsaPublicKey {
         Ok(Self {
             public_key: PKey::from_ec_key(k)?,
             algorithm,
+            low_s: false,
+            tolerate_short_sig: false,
         })
     }
 }
@@ -321,7 +481,17 @@ impl SigningKey for EcdsaPrivateKey {
 
         // Use the low-level signing API we get the `r`, `s` bytes more easily:
         // No need to parse the ASN.1 DER encoded signature.
-        let sig = EcdsaSig::sign(&hash, self.private_key.ec_key()?.as_ref())?;
+        let ec = self.private_key.ec_key()?;
+        let sig = if self.deterministic {
+            sign_deterministic(self.algorithm.digest(), &hash, &self.private_key)?
+        } else {
+            EcdsaSig::sign(&hash, ec.as_ref())?
+        };
+        let normalized_s = if self.low_s {
+            to_low_s(ec.group(), sig.s())?
+        } else {
+            None
+        };
 
         let sig_len = self.algorithm.len();
         let mut out = smallvec![0u8; sig_len];
@@ -330,7 +500,7 @@ impl SigningKey for EcdsaPrivateKey {
         let r_len = r.num_bytes() as usize;
         debug_assert!(r_len <= sig_len / 2);
 
-        let s = sig.s();
+        let s = normalized_s.as_deref().unwrap_or(sig.s());
         let s_len = s.num_bytes() as usize;
         debug_assert!(s_len <= sig_len / 2);
 
@@ -345,44 +515,166 @@ impl SigningKey for EcdsaPrivateKey {
     }
 }
 
+// ECDSA with the nonce generated as described in RFC 6979, by OpenSSL.
+#[cfg(ossl320)]
+fn sign_deterministic(
+    digest: MessageDigest,
+    hash: &[u8],
+    private_key: &PKeyRef<Private>,
+) -> Result<EcdsaSig> {
+    let mut ctx = PkeyCtx::new(private_key)?;
+    ctx.sign_init()?;
+    // The digest is also the one used for the HMAC-DRBG of RFC 6979.
+    ctx.set_signature_md(Md::from_nid(digest.type_()).ok_or(Error::UnsupportedOrInvalidKey)?)?;
+    ctx.set_nonce_type(NonceType::DETERMINISTIC_K)?;
+    let mut der = Vec::new();
+    ctx.sign_to_vec(hash, &mut der)?;
+    Ok(EcdsaSig::from_der(&der)?)
+}
+
+// Deterministic nonces need OpenSSL 3.2.
+#[cfg(not(ossl320))]
+fn sign_deterministic(
+    _digest: MessageDigest,
+    _hash: &[u8],
+    _private_key: &PKeyRef<Private>,
+) -> Result<EcdsaSig> {
+    Err(Error::UnsupportedOrInvalidKey)
+}
+
+// If `s` is in the high-S form, i.e. `s > n / 2`, return `n - s`.
+fn to_low_s(group: &EcGroupRef, s: &BigNumRef) -> Result<Option<BigNum>> {
+    let mut ctx = BigNumContext::new()?;
+    let mut order = BigNum::new()?;
+    group.order(&mut order, &mut ctx)?;
+    let mut half_order = BigNum::new()?;
+    half_order.rshift1(&order)?;
+    if s.ucmp(&half_order) != Ordering::Greater {
+        return Ok(None);
+    }
+    let mut low_s = BigNum::new()?;
+    low_s.checked_sub(&order, s)?;
+    Ok(Some(low_s))
+}
+
+fn hash_reader(digest: MessageDigest, input: &mut dyn Read) -> Result<DigestBytes> {
+    let mut hasher = Hasher::new(digest)?;
+    std::io::copy(input, &mut hasher)?;
+    Ok(hasher.finish()?)
+}
+
 fn ecdsa_verify<T: HasPublic>(
     alg: EcdsaAlgorithm,
     k: &PKeyRef<T>,
-    v: &[u8],
+    low_s: bool,
+    tolerate_short_sig: bool,
+    hash: &[u8],
     sig: &[u8],
 ) -> Result<()> {
-    if sig.len() != alg.len() {
-        return Err(Error::VerificationError);
-    }
-    // There may be some leading zero bytes in r and s, but it does not matter.
-    let (r, s) = sig.split_at(alg.len() / 2);
-    let sig = EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
-    let hash = hash(alg.digest(), v)?;
-    if sig.verify(&hash, k.ec_key()?.as_ref())? {
-        Ok(())
+    let half = alg.len() / 2;
+    // Where `r` ends. A short signature is ambiguous, as either `r` or `s`
+    // may have lost leading zeros, so try every possible split.
+    let splits = if sig.len() == alg.len() {
+        half..=half
+    } else if tolerate_short_sig && sig.len() < alg.len() && sig.len() > half {
+        sig.len() - half..=half
     } else {
-        Err(Error::VerificationError)
+        return Err(Error::VerificationError);
+    };
+    let ec = k.ec_key()?;
+    for split in splits {
+        // There may be some leading zero bytes in r and s, but it does not matter.
+        let (r, s) = sig.split_at(split);
+        let (r, s) = (BigNum::from_slice(r)?, BigNum::from_slice(s)?);
+        if low_s && to_low_s(ec.group(), &s)?.is_some() {
+            continue;
+        }
+        let sig = EcdsaSig::from_private_components(r, s)?;
+        if sig.verify(hash, ec.as_ref())? {
+            return Ok(());
+        }
     }
+    Err(Error::VerificationError)
 }
 
 impl VerificationKey for EcdsaPrivateKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "EC")?;
         // ECDSA keys can handle only one algorithm.
         if alg != self.algorithm.name() {
             return Err(Error::VerificationError);
         }
 
-        ecdsa_verify(self.algorithm, self.private_key.as_ref(), v, sig)
+        ecdsa_verify(
+            self.algorithm,
+            self.private_key.as_ref(),
+            self.low_s,
+            self.tolerate_short_sig,
+            &hash(self.algorithm.digest(), v)?,
+            sig,
+        )
+    }
+
+    fn verify_reader(&self, input: &mut dyn Read, sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "EC")?;
+        if alg != self.algorithm.name() {
+            return Err(Error::VerificationError);
+        }
+
+        ecdsa_verify(
+            self.algorithm,
+            self.private_key.as_ref(),
+            self.low_s,
+            self.tolerate_short_sig,
+            &hash_reader(self.algorithm.digest(), input)?,
+            sig,
+        )
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        vec![self.algorithm.name()]
+    }
+
+    fn can_sign(&self) -> bool {
+        true
     }
 }
 
 impl VerificationKey for EcdsaPublicKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "EC")?;
+        if alg != self.algorithm.name() {
+            return Err(Error::VerificationError);
+        }
+
+        ecdsa_verify(
+            self.algorithm,
+            self.public_key.as_ref(),
+            self.low_s,
+            self.tolerate_short_sig,
+            &hash(self.algorithm.digest(), v)?,
+            sig,
+        )
+    }
+
+    fn verify_reader(&self, input: &mut dyn Read, sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "EC")?;
         if alg != self.algorithm.name() {
             return Err(Error::VerificationError);
         }
 
-        ecdsa_verify(self.algorithm, self.public_key.as_ref(), v, sig)
+        ecdsa_verify(
+            self.algorithm,
+            self.public_key.as_ref(),
+            self.low_s,
+            self.tolerate_short_sig,
+            &hash_reader(self.algorithm.digest(), input)?,
+            sig,
+        )
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        vec![self.algorithm.name()]
     }
 }
 
@@ -405,11 +697,11 @@ mod tests {
         assert!(EcdsaPublicKey::from_pem(&secp192k1_k_pub_pem).is_err());
 
         // Should be able to handle BEGIN EC PRIVATE KEY as well.
-        let ec_pem = k.private_key.ec_key()?.private_key_to_pem()?;
-        assert!(std::str::from_utf8(&ec_pem)
-            .unwrap()
-            .contains("BEGIN EC PRIVATE KEY"));
-        EcdsaPrivateKey::from_pem(&ec_pem)?;
+        let ec_pem = k.private_key_to_pem_sec1()?;
+        assert!(ec_pem.contains("BEGIN EC PRIVATE KEY"));
+        let k1 = EcdsaPrivateKey::from_pem(ec_pem.as_bytes())?;
+        assert!(k.private_key.public_eq(k1.private_key.as_ref()));
+        assert_eq!(k.d()?, k1.d()?);
 
         let pk_pem = k.public_key_to_pem()?;
 
@@ -426,7 +718,10 @@ mod tests {
 
         assert_eq!((&x, &y), (&x1, &y1));
 
-        EcdsaPublicKey::from_coordinates(&x, &y, EcdsaAlgorithm::ES256)?;
+        assert_eq!(
+            EcdsaPublicKey::from_coordinates(&x, &y, EcdsaAlgorithm::ES256)?,
+            pk
+        );
 
         if let SomePrivateKey::Ecdsa(k1) = k
             .private_key_to_jwk()?
@@ -463,4 +758,188 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn low_s() -> Result<()> {
+        for alg in [
+            EcdsaAlgorithm::ES256,
+            EcdsaAlgorithm::ES256K,
+            EcdsaAlgorithm::ES384,
+            EcdsaAlgorithm::ES512,
+        ] {
+            let mut k = EcdsaPrivateKey::generate(alg)?;
+            k.low_s = true;
+            let (x, y) = k.coordinates()?;
+            let mut pk = EcdsaPublicKey::from_coordinates(&x, &y, alg)?;
+            let group = EcGroup::from_curve_name(alg.curve())?;
+            let mut ctx = BigNumContext::new()?;
+            let mut order = BigNum::new()?;
+            group.order(&mut order, &mut ctx)?;
+
+            for _ in 0..8 {
+                let sig = k.sign(b"...")?;
+                let (r, s) = sig.split_at(alg.len() / 2);
+                let s = BigNum::from_slice(s)?;
+                assert!(to_low_s(&group, &s)?.is_none());
+
+                // Flip to the equally valid high-S form.
+                let mut high_s = BigNum::new()?;
+                high_s.checked_sub(&order, &s)?;
+                let mut high_sig = r.to_vec();
+                high_sig.extend_from_slice(&high_s.to_vec_padded(alg.len() as i32 / 2)?);
+
+                pk.low_s = false;
+                assert!(pk.verify(b"...", &sig, alg.name()).is_ok());
+                assert!(pk.verify(b"...", &high_sig, alg.name()).is_ok());
+                pk.low_s = true;
+                assert!(pk.verify(b"...", &sig, alg.name()).is_ok());
+                assert!(pk.verify(b"...", &high_sig, alg.name()).is_err());
+                assert!(k.verify(b"...", &high_sig, alg.name()).is_err());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_seed() -> Result<()> {
+        for alg in [
+            EcdsaAlgorithm::ES256,
+            EcdsaAlgorithm::ES256K,
+            EcdsaAlgorithm::ES384,
+            EcdsaAlgorithm::ES512,
+        ] {
+            let k = EcdsaPrivateKey::from_seed(alg, &[7; 32])?;
+            let k1 = EcdsaPrivateKey::from_seed(alg, &[7; 32])?;
+            let k2 = EcdsaPrivateKey::from_seed(alg, &[8; 32])?;
+            assert_eq!(
+                k.private_key_to_pem_pkcs8()?,
+                k1.private_key_to_pem_pkcs8()?
+            );
+            assert_ne!(
+                k.private_key_to_pem_pkcs8()?,
+                k2.private_key_to_pem_pkcs8()?
+            );
+            let sig = k.sign(b"...")?;
+            k1.verify(b"...", &sig, alg.name())?;
+            assert!(EcdsaPrivateKey::from_seed(alg, &[0; 32]).is_err());
+        }
+        Ok(())
+    }
+
+    #[cfg(ossl320)]
+    #[test]
+    fn deterministic() -> Result<()> {
+        for alg in [
+            EcdsaAlgorithm::ES256,
+            EcdsaAlgorithm::ES256K,
+            EcdsaAlgorithm::ES384,
+            EcdsaAlgorithm::ES512,
+        ] {
+            let mut k = EcdsaPrivateKey::generate(alg)?;
+            k.deterministic = true;
+            let sig = k.sign(b"...")?;
+            assert_eq!(sig, k.sign(b"...")?);
+            assert_ne!(sig, k.sign(b"....")?);
+            k.verify(b"...", &sig, alg.name())?;
+            k.low_s = true;
+            k.verify(b"....", &k.sign(b"....")?, alg.name())?;
+        }
+
+        // RFC 6979 A.2.5, P-256 with SHA-256, message "sample".
+        let mut x = [0u8; 32];
+        x.copy_from_slice(
+            &BigNum::from_hex_str(
+                "C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721",
+            )?
+            .to_vec(),
+        );
+        let mut k = EcdsaPrivateKey::from_seed(EcdsaAlgorithm::ES256, &x)?;
+        k.deterministic = true;
+        let expected = BigNum::from_hex_str(concat!(
+            "EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716",
+            "F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8",
+        ))?
+        .to_vec();
+        assert_eq!(&k.sign(b"sample")?[..], &expected[..]);
+        Ok(())
+    }
+
+    #[cfg(not(ossl320))]
+    #[test]
+    fn deterministic_unsupported() -> Result<()> {
+        let mut k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        k.deterministic = true;
+        assert!(matches!(
+            k.sign(b"..."),
+            Err(Error::UnsupportedOrInvalidKey)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn tolerate_short_sig() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut pk = EcdsaPublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        for strip_s in [false, true] {
+            // Find a signature whose `r` or `s` has a leading zero.
+            let at = if strip_s { 32 } else { 0 };
+            let (msg, sig) = (0u32..)
+                .map(|i| i.to_string())
+                .map(|msg| k.sign(msg.as_bytes()).map(|sig| (msg, sig)))
+                .find(|res| !matches!(res, Ok((_, sig)) if sig[at] != 0))
+                .unwrap()?;
+            let mut short = sig.to_vec();
+            short.remove(at);
+
+            pk.tolerate_short_sig = false;
+            pk.verify(msg.as_bytes(), &sig, "ES256")?;
+            assert!(pk.verify(msg.as_bytes(), &short, "ES256").is_err());
+
+            pk.tolerate_short_sig = true;
+            pk.verify(msg.as_bytes(), &sig, "ES256")?;
+            pk.verify(msg.as_bytes(), &short, "ES256")?;
+            assert!(pk.verify(b"other", &short, "ES256").is_err());
+            assert!(pk.verify(msg.as_bytes(), &short[..32], "ES256").is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn to_jwk() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let public = k.to_jwk(false)?;
+        assert!(public.d.is_none());
+        assert!(public.x.is_some());
+        let private = k.to_jwk(true)?;
+        assert!(private.d.is_some());
+        assert_eq!(private.x, public.x);
+        Ok(())
+    }
+
+    #[test]
+    fn jwk_fixed_length() -> Result<()> {
+        for alg in [
+            EcdsaAlgorithm::ES256,
+            EcdsaAlgorithm::ES256K,
+            EcdsaAlgorithm::ES384,
+            EcdsaAlgorithm::ES512,
+        ] {
+            let encoded_len = URL_SAFE_TRAILING_BITS.encode(vec![0; alg.len() / 2]).len();
+            for i in 1..=255u8 {
+                let mut seed = [0; 32];
+                seed[31] = i;
+                let k = EcdsaPrivateKey::from_seed(alg, &seed)?;
+                let jwk = k.to_jwk(true)?;
+                for c in [&jwk.d, &jwk.x, &jwk.y] {
+                    assert_eq!(c.as_ref().map(|c| c.len()), Some(encoded_len));
+                }
+                if let SomePrivateKey::Ecdsa(k1) = jwk.to_signing_key(RsaAlgorithm::PS256)? {
+                    assert_eq!(k.d()?, k1.d()?);
+                } else {
+                    panic!("expected ecdsa private key");
+                }
+            }
+        }
+        Ok(())
+    }
 }
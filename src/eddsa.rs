@@ -1,6 +1,6 @@
 use crate::{
-    jwk::Jwk, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey, VerificationKey,
-    URL_SAFE_TRAILING_BITS,
+    jwk::Jwk, Algorithm, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey,
+    VerificationKey, URL_SAFE_TRAILING_BITS,
 };
 use base64::Engine as _;
 use foreign_types::ForeignType;
@@ -12,17 +12,34 @@ use openssl::{
 use smallvec::SmallVec;
 use std::ptr;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Ed25519PrivateKey {
     private_key: PKey<Private>,
 }
 
+impl std::fmt::Debug for Ed25519PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519PrivateKey")
+            .field("private_key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
 impl Ed25519PrivateKey {
     pub fn generate() -> Result<Self> {
         let pkey = PKey::generate_ed25519()?;
         Ok(Self { private_key: pkey })
     }
 
+    /// Create a key from a fixed 32-byte seed, e.g. for reproducible test
+    /// fixtures. For Ed25519 the seed is the private key, so this is the same
+    /// as [`from_bytes`](Self::from_bytes).
+    ///
+    /// Use [`generate`](Self::generate) for real keys.
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self> {
+        Self::from_bytes(seed)
+    }
+
     pub fn from_bytes(b: &[u8]) -> Result<Self> {
         let pkey = unsafe {
             openssl_sys::EVP_PKEY_new_raw_private_key(
@@ -98,6 +115,8 @@ impl PublicKeyToJwk for Ed25519PrivateKey {
         let bytes: [u8; 32] = self.public_key_bytes()?;
         Ok(Jwk {
             kty: "OKP".into(),
+            use_: Some("sig".into()),
+            alg: Some("EdDSA".into()),
             crv: Some("Ed25519".into()),
             x: Some(URL_SAFE_TRAILING_BITS.encode(bytes)),
             ..Jwk::default()
@@ -111,6 +130,8 @@ impl PrivateKeyToJwk for Ed25519PrivateKey {
         let x: [u8; 32] = self.public_key_bytes()?;
         Ok(Jwk {
             kty: "OKP".into(),
+            use_: Some("sig".into()),
+            alg: Some("EdDSA".into()),
             crv: Some("Ed25519".into()),
             d: Some(URL_SAFE_TRAILING_BITS.encode(d)),
             x: Some(URL_SAFE_TRAILING_BITS.encode(x)),
@@ -119,11 +140,20 @@ impl PrivateKeyToJwk for Ed25519PrivateKey {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ed25519PublicKey {
     public_key: PKey<Public>,
 }
 
+/// Compares the key material only.
+impl PartialEq for Ed25519PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_key.public_eq(&other.public_key)
+    }
+}
+
+impl Eq for Ed25519PublicKey {}
+
 impl Ed25519PublicKey {
     pub(crate) fn from_pkey(pkey: PKey<Public>) -> Result<Self> {
         if pkey.id() != openssl::pkey::Id::ED25519 {
@@ -179,6 +209,8 @@ impl PublicKeyToJwk for Ed25519PublicKey {
         let bytes: [u8; 32] = self.to_bytes()?;
         Ok(Jwk {
             kty: "OKP".into(),
+            use_: Some("sig".into()),
+            alg: Some("EdDSA".into()),
             crv: Some("Ed25519".into()),
             x: Some(URL_SAFE_TRAILING_BITS.encode(bytes)),
             ..Jwk::default()
@@ -204,6 +236,7 @@ impl SigningKey for Ed25519PrivateKey {
 
 impl VerificationKey for Ed25519PrivateKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "OKP")?;
         if alg != "EdDSA" {
             return Err(Error::VerificationError);
         }
@@ -215,10 +248,18 @@ impl VerificationKey for Ed25519PrivateKey {
             Err(Error::VerificationError)
         }
     }
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        vec!["EdDSA"]
+    }
+
+    fn can_sign(&self) -> bool {
+        true
+    }
 }
 
 impl VerificationKey for Ed25519PublicKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "OKP")?;
         if alg != "EdDSA" {
             return Err(Error::VerificationError);
         }
@@ -230,6 +271,9 @@ impl VerificationKey for Ed25519PublicKey {
             Err(Error::VerificationError)
         }
     }
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        vec!["EdDSA"]
+    }
 }
 
 #[cfg(test)]
@@ -272,6 +316,7 @@ mod tests {
         let pk_pem1 = pk.to_pem()?;
 
         assert_eq!(pk_pem, pk_pem1);
+        assert_eq!(Ed25519PublicKey::from_bytes(&pk.to_bytes()?)?, pk);
 
         if let SomePrivateKey::Ed25519(k1) = k
             .private_key_to_jwk()?
@@ -288,6 +333,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn jwk_round_trip() -> Result<()> {
+        let k = Ed25519PrivateKey::generate()?;
+
+        let jwk = k.private_key_to_jwk()?;
+        assert_eq!(jwk.alg.as_deref(), Some("EdDSA"));
+        let jwk: Jwk = serde_json::from_str(&serde_json::to_string(&jwk)?)?;
+        if let SomePrivateKey::Ed25519(k1) = jwk.to_signing_key(RsaAlgorithm::PS256)? {
+            assert_eq!(k.private_key_bytes()?, k1.private_key_bytes()?);
+        } else {
+            panic!("expected ed25519 private key");
+        }
+
+        let jwk = k.public_key_to_jwk()?;
+        assert_eq!(jwk.alg.as_deref(), Some("EdDSA"));
+        assert_eq!(jwk.d, None);
+        let jwk: Jwk = serde_json::from_str(&serde_json::to_string(&jwk)?)?;
+        let pk = jwk.to_verification_key()?;
+        assert_eq!(pk.public_key_to_jwk()?.x, jwk.x);
+        let sig = k.sign(b"...")?;
+        pk.verify(b"...", &sig, "EdDSA")?;
+
+        // Wrong key lengths.
+        let mut short = k.public_key_to_jwk()?;
+        short.x = Some(URL_SAFE_TRAILING_BITS.encode([1u8; 31]));
+        assert!(short.to_verification_key().is_err());
+        let mut short = k.private_key_to_jwk()?;
+        short.d = Some(URL_SAFE_TRAILING_BITS.encode([1u8; 33]));
+        assert!(short.to_signing_key(RsaAlgorithm::PS256).is_err());
+
+        // Mismatching public key.
+        let mut mismatch = k.private_key_to_jwk()?;
+        mismatch.x = Ed25519PrivateKey::generate()?.public_key_to_jwk()?.x;
+        assert!(mismatch.to_signing_key(RsaAlgorithm::PS256).is_err());
+
+        // Wrong alg.
+        let mut wrong_alg = k.public_key_to_jwk()?;
+        wrong_alg.alg = Some("ES256".into());
+        assert!(wrong_alg.to_verification_key().is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn sign_verify() -> Result<()> {
         let k = Ed25519PrivateKey::generate()?;
@@ -300,4 +388,22 @@ mod tests {
         assert!(pk.verify(b"...", &sig[..63], "EdDSA").is_err());
         Ok(())
     }
+
+    #[test]
+    fn from_seed() -> Result<()> {
+        // RFC 8032 section 7.1, test 1.
+        let seed = [
+            0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec,
+            0x2c, 0xc4, 0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03,
+            0x1c, 0xae, 0x7f, 0x60,
+        ];
+        let public_key = [
+            0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64,
+            0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68,
+            0xf7, 0x07, 0x51, 0x1a,
+        ];
+        let k = Ed25519PrivateKey::from_seed(&seed)?;
+        assert_eq!(k.public_key_bytes()?, public_key);
+        Ok(())
+    }
 }
@@ -1,7 +1,9 @@
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine as _};
 use openssl::{hash::MessageDigest, memcmp, pkey::PKey, rand::rand_bytes, sign::Signer};
 use smallvec::{smallvec, SmallVec};
+use std::io::Read;
 
-use crate::{Error, Result, SigningKey, VerificationKey};
+use crate::{Algorithm, Error, Result, SigningKey, VerificationKey, URL_SAFE_TRAILING_BITS};
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,7 +14,7 @@ pub enum HmacAlgorithm {
 }
 
 impl HmacAlgorithm {
-    fn name(self) -> &'static str {
+    pub fn name(self) -> &'static str {
         use HmacAlgorithm::*;
         match self {
             HS256 => "HS256",
@@ -21,6 +23,23 @@ impl HmacAlgorithm {
         }
     }
 
+    /// Parse a name from e.g. a config file: surrounding whitespace is
+    /// ignored and the match is case-insensitive, so ` hs256 ` is `HS256`.
+    ///
+    /// Header `alg` values are always matched exactly.
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::from_exact_name(&name.trim().to_ascii_uppercase())
+    }
+
+    pub(crate) fn from_exact_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "HS256" => HmacAlgorithm::HS256,
+            "HS384" => HmacAlgorithm::HS384,
+            "HS512" => HmacAlgorithm::HS512,
+            _ => return Err(Error::UnsupportedOrInvalidKey),
+        })
+    }
+
     fn digest(self) -> MessageDigest {
         use HmacAlgorithm::*;
         match self {
@@ -31,12 +50,22 @@ impl HmacAlgorithm {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct HmacKey {
     k: SmallVec<[u8; 32]>,
     algorithm: HmacAlgorithm,
 }
 
+// Don't leak the secret into logs.
+impl std::fmt::Debug for HmacKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacKey")
+            .field("alg", &self.algorithm)
+            .field("key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
 impl HmacKey {
     #[inline]
     pub fn generate(algorithm: HmacAlgorithm) -> Result<Self> {
@@ -62,10 +91,28 @@ impl HmacKey {
         }
     }
 
+    /// Decode a base64url secret, e.g. the `k` field of an `oct` JWK. Padding
+    /// is allowed.
+    pub fn from_base64url(k: &str, algorithm: HmacAlgorithm) -> Result<Self> {
+        let k = URL_SAFE_TRAILING_BITS.decode(k.trim_end_matches('='))?;
+        Ok(Self::from_bytes(&k, algorithm))
+    }
+
+    /// Decode a standard base64 secret. Padding is allowed.
+    pub fn from_base64_standard(k: &str, algorithm: HmacAlgorithm) -> Result<Self> {
+        let k = STANDARD_NO_PAD.decode(k.trim_end_matches('='))?;
+        Ok(Self::from_bytes(&k, algorithm))
+    }
+
     #[inline]
     pub fn serialize(&self) -> &[u8] {
         &self.k
     }
+
+    /// The secret as unpadded base64url.
+    pub fn to_base64url(&self) -> String {
+        URL_SAFE_TRAILING_BITS.encode(&self.k)
+    }
 }
 
 impl SigningKey for HmacKey {
@@ -86,6 +133,7 @@ impl SigningKey for HmacKey {
 
 impl VerificationKey for HmacKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "oct")?;
         if alg != self.algorithm.name() {
             return Err(Error::VerificationError);
         }
@@ -98,12 +146,48 @@ impl VerificationKey for HmacKey {
             Err(Error::VerificationError)
         }
     }
+
+    fn verify_reader(&self, input: &mut dyn Read, sig: &[u8], alg: &str) -> Result<()> {
+        Algorithm::check_key_type(alg, "oct")?;
+        if alg != self.algorithm.name() {
+            return Err(Error::VerificationError);
+        }
+
+        let pk = PKey::hmac(&self.k)?;
+        let mut signer = Signer::new(self.algorithm.digest(), pk.as_ref())?;
+        std::io::copy(input, &mut signer)?;
+        let expected = signer.sign_to_vec()?;
+
+        if memcmp::eq(sig, &expected) {
+            Ok(())
+        } else {
+            Err(Error::VerificationError)
+        }
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        vec![self.algorithm.name()]
+    }
+
+    fn can_sign(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn debug_redacts_key() -> Result<()> {
+        let k = HmacKey::from_bytes(b"secret", HmacAlgorithm::HS256);
+        assert_eq!(
+            format!("{:?}", k),
+            "HmacKey { alg: HS256, key: <redacted> }"
+        );
+        Ok(())
+    }
+
     #[test]
     fn conversion() -> Result<()> {
         let k = HmacKey::generate(HmacAlgorithm::HS384)?;
@@ -115,6 +199,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn base64() -> Result<()> {
+        let k = HmacKey::from_base64url("-_8", HmacAlgorithm::HS256)?;
+        assert_eq!(k.serialize(), [0xfb, 0xff]);
+        assert_eq!(k.to_base64url(), "-_8");
+        assert_eq!(
+            HmacKey::from_base64url("-_8=", HmacAlgorithm::HS256)?.serialize(),
+            k.serialize()
+        );
+        assert_eq!(
+            HmacKey::from_base64_standard("+/8=", HmacAlgorithm::HS256)?.serialize(),
+            k.serialize()
+        );
+        assert!(matches!(
+            HmacKey::from_base64url("+/8=", HmacAlgorithm::HS256),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            HmacKey::from_base64_standard("-_8", HmacAlgorithm::HS256),
+            Err(Error::Decode(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify() -> Result<()> {
         for alg in [
@@ -0,0 +1,429 @@
+// Parsing JSON while rejecting objects with duplicate keys, in the same pass.
+//
+// `Checked` wraps every deserializer, visitor, seed and access that the
+// deserialized type is handed, and `CheckedMap` records the keys of each
+// object as they are read.
+
+use std::{borrow::Cow, cell::Cell, collections::HashSet, fmt};
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use smallvec::SmallVec;
+
+use crate::{Error, Result};
+
+/// Like `serde_json::from_slice`, but fails with [`Error::MalformedToken`] if
+/// any object in `json` has duplicate keys.
+pub(crate) fn from_slice_unique_keys<T: DeserializeOwned>(json: &[u8]) -> Result<T> {
+    let found = Cell::new(false);
+    let mut de = serde_json::Deserializer::from_slice(json);
+    let value = T::deserialize(Checked {
+        inner: &mut de,
+        found: &found,
+        key: None,
+    });
+    if found.get() {
+        return Err(Error::MalformedToken);
+    }
+    let value = value?;
+    de.end()?;
+    Ok(value)
+}
+
+struct Checked<'a, 'de, T> {
+    inner: T,
+    // Set when a duplicate key is found, to tell it apart from other errors.
+    found: &'a Cell<bool>,
+    // Where to put the string this deserializes to, if it's an object key.
+    key: Option<&'a mut Option<Cow<'de, str>>>,
+}
+
+impl<'a, 'de, T> Checked<'a, 'de, T> {
+    fn new(inner: T, found: &'a Cell<bool>) -> Self {
+        Self {
+            inner,
+            found,
+            key: None,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {$(
+        fn $method<V: Visitor<'de>>(
+            self,
+            $($arg: $ty,)*
+            visitor: V,
+        ) -> std::result::Result<V::Value, D::Error> {
+            self.inner.$method($($arg,)* Checked {
+                inner: visitor,
+                found: self.found,
+                key: self.key,
+            })
+        }
+    )*};
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Checked<'_, 'de, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {$(
+        fn $method<E: de::Error>(self, v: $ty) -> std::result::Result<V::Value, E> {
+            self.inner.$method(v)
+        }
+    )*};
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Checked<'_, 'de, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<V::Value, E> {
+        if let Some(key) = self.key {
+            *key = Some(Cow::Owned(v.into()));
+        }
+        self.inner.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> std::result::Result<V::Value, E> {
+        if let Some(key) = self.key {
+            *key = Some(Cow::Borrowed(v));
+        }
+        self.inner.visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<V::Value, E> {
+        if let Some(key) = self.key {
+            *key = Some(Cow::Owned(v.clone()));
+        }
+        self.inner.visit_string(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<V::Value, D::Error> {
+        self.inner
+            .visit_some(Checked::new(deserializer, self.found))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<V::Value, D::Error> {
+        self.inner
+            .visit_newtype_struct(Checked::new(deserializer, self.found))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> std::result::Result<V::Value, A::Error> {
+        self.inner.visit_seq(Checked::new(seq, self.found))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<V::Value, A::Error> {
+        self.inner.visit_map(CheckedMap {
+            inner: map,
+            found: self.found,
+            keys: Keys::default(),
+        })
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> std::result::Result<V::Value, A::Error> {
+        self.inner.visit_enum(Checked::new(data, self.found))
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Checked<'_, 'de, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<S::Value, D::Error> {
+        self.inner.deserialize(Checked {
+            inner: deserializer,
+            found: self.found,
+            key: self.key,
+        })
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Checked<'_, 'de, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> std::result::Result<Option<T::Value>, A::Error> {
+        self.inner.next_element_seed(Checked::new(seed, self.found))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for Checked<'a, 'de, A> {
+    type Error = A::Error;
+    type Variant = Checked<'a, 'de, A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> std::result::Result<(T::Value, Self::Variant), A::Error> {
+        let (value, variant) = self.inner.variant_seed(Checked::new(seed, self.found))?;
+        Ok((value, Checked::new(variant, self.found)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Checked<'_, 'de, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> std::result::Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> std::result::Result<T::Value, A::Error> {
+        self.inner
+            .newtype_variant_seed(Checked::new(seed, self.found))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> std::result::Result<V::Value, A::Error> {
+        self.inner
+            .tuple_variant(len, Checked::new(visitor, self.found))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, A::Error> {
+        self.inner
+            .struct_variant(fields, Checked::new(visitor, self.found))
+    }
+}
+
+struct CheckedMap<'a, 'de, A> {
+    inner: A,
+    found: &'a Cell<bool>,
+    keys: Keys<'de>,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for CheckedMap<'_, 'de, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, A::Error> {
+        let mut key = None;
+        let value = self.inner.next_key_seed(Checked {
+            inner: seed,
+            found: self.found,
+            key: Some(&mut key),
+        })?;
+        if let Some(key) = key {
+            if !self.keys.insert(key) {
+                self.found.set(true);
+                return Err(de::Error::custom("duplicate key in JSON object"));
+            }
+        }
+        Ok(value)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> std::result::Result<V::Value, A::Error> {
+        self.inner.next_value_seed(Checked::new(seed, self.found))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+// The keys of an object so far. Small objects, i.e. all of them in a typical
+// token, are searched linearly without allocating; larger ones are hashed so
+// that a token with many keys can't make this quadratic.
+#[derive(Default)]
+struct Keys<'de> {
+    small: SmallVec<[Cow<'de, str>; 16]>,
+    large: Option<HashSet<Cow<'de, str>>>,
+}
+
+impl<'de> Keys<'de> {
+    // Returns `false` if `key` was already there.
+    fn insert(&mut self, key: Cow<'de, str>) -> bool {
+        if let Some(large) = &mut self.large {
+            return large.insert(key);
+        }
+        if self.small.contains(&key) {
+            return false;
+        }
+        if self.small.len() == self.small.inline_size() {
+            let mut large: HashSet<_> = self.small.drain(..).collect();
+            large.insert(key);
+            self.large = Some(large);
+        } else {
+            self.small.push(key);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn duplicate_keys() -> Result<()> {
+        let v: Value = from_slice_unique_keys(br#"{"a": [{"b": 1, "c": {"b": 2}}], "b": null}"#)?;
+        assert_eq!(v["a"][0]["c"]["b"], 2);
+
+        for json in [
+            &br#"{"a": 1, "a": 2}"#[..],
+            br#"{"a": 1, "\u0061": 2}"#,
+            br#"{"a": [{"b": 1, "b": 2}]}"#,
+            br#"{"a": {"b": {"c": 1, "c": 1}}}"#,
+        ] {
+            assert!(matches!(
+                from_slice_unique_keys::<Value>(json),
+                Err(Error::MalformedToken)
+            ));
+        }
+
+        // Past the inline size, too.
+        let mut json: String = (0..40).map(|i| format!("\"k{}\": {},", i, i)).collect();
+        json.insert(0, '{');
+        json.push_str("\"k30\": 0}");
+        assert!(matches!(
+            from_slice_unique_keys::<BTreeMap<String, u32>>(json.as_bytes()),
+            Err(Error::MalformedToken)
+        ));
+
+        // Other errors are left alone.
+        assert!(matches!(
+            from_slice_unique_keys::<Value>(b"{\"a\": 1} x"),
+            Err(Error::SerdeJson(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn nested_types() -> Result<()> {
+        #[derive(Deserialize)]
+        enum E {
+            S { x: BTreeMap<String, u32> },
+        }
+        #[derive(Deserialize)]
+        struct T {
+            e: E,
+            o: Option<BTreeMap<String, u32>>,
+            #[serde(flatten)]
+            rest: BTreeMap<String, Value>,
+        }
+
+        let t: T =
+            from_slice_unique_keys(br#"{"e": {"S": {"x": {"a": 1}}}, "o": {"a": 1}, "z": 1}"#)?;
+        assert!(matches!(&t.e, E::S { x } if x["a"] == 1));
+        assert_eq!(t.o.unwrap()["a"], 1);
+        assert_eq!(t.rest["z"], 1);
+
+        for json in [
+            &br#"{"e": {"S": {"x": {"a": 1, "a": 2}}}, "o": null}"#[..],
+            br#"{"e": {"S": {"x": {}}}, "o": {"a": 1, "a": 2}}"#,
+            br#"{"e": {"S": {"x": {}}}, "o": null, "z": 1, "z": 2}"#,
+        ] {
+            assert!(matches!(
+                from_slice_unique_keys::<T>(json),
+                Err(Error::MalformedToken)
+            ));
+        }
+        Ok(())
+    }
+}
@@ -3,29 +3,69 @@
 //! Only public keys are really supported for now.
 
 use crate::{
+    decode_header,
     ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey, EcdsaPublicKey},
     eddsa::{Ed25519PrivateKey, Ed25519PublicKey},
     rsa::{RsaAlgorithm, RsaPrivateKey, RsaPublicKey},
     some::SomePublicKey,
-    verify, verify_only, Error, Header, HeaderAndClaims, PublicKeyToJwk, Result, SigningKey,
-    SomePrivateKey, VerificationKey, URL_SAFE_TRAILING_BITS,
+    verify, verify_only, verify_with_options, Algorithm, Error, Header, HeaderAndClaims, KeyPolicy,
+    PublicKeyToJwk, Result, SigningKey, SomePrivateKey, VerificationKey, VerifyOptions,
+    URL_SAFE_TRAILING_BITS,
 };
 use base64::Engine as _;
 use openssl::{
     bn::BigNum,
     hash::{hash, MessageDigest},
     pkey::PKey,
-    rsa::{Rsa, RsaPrivateKeyBuilder},
+    rsa::Rsa,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    str::FromStr,
+};
 
 // TODO: private key jwk.
 
+/// Decode a base64url JWK member.
+///
+/// JWK values should be unpadded base64url, but some producers pad them or
+/// use the standard alphabet, so accept those too.
+fn decode_field(value: &str) -> Result<Vec<u8>> {
+    let value = value.trim_end_matches('=');
+    if value.contains(['+', '/']) {
+        let value = value.replace('+', "-").replace('/', "_");
+        Ok(URL_SAFE_TRAILING_BITS.decode(value)?)
+    } else {
+        Ok(URL_SAFE_TRAILING_BITS.decode(value)?)
+    }
+}
+
+// An RSA integer as the base64url of its big-endian bytes without leading
+// zeros, which is how RFC 7518 says to represent `n` and `e`.
+fn canonical_uint(value: &str) -> Result<String> {
+    let bytes = decode_field(value)?;
+    Ok(URL_SAFE_TRAILING_BITS.encode(crate::rsa::strip_leading_zeros(&bytes)))
+}
+
+fn deserialize_kid<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Value::String(kid)) => Ok(Some(kid)),
+        Some(Value::Number(kid)) => Ok(Some(kid.to_string())),
+        Some(_) => Err(serde::de::Error::custom("kid must be a string")),
+    }
+}
+
 /// JWK Representation.
+///
+/// `Debug` doesn't print the private key members.
 #[non_exhaustive]
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize, Default)]
 pub struct Jwk {
     pub kty: String,
     #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
@@ -36,7 +76,12 @@ pub struct Jwk {
     pub alg: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub crv: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Numbers are accepted and converted to strings.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_kid",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub kid: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -65,8 +110,63 @@ pub struct Jwk {
     pub oth: Vec<Value>,
 }
 
+impl std::fmt::Debug for Jwk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn redacted<T>(v: &Option<T>) -> Option<std::fmt::Arguments<'static>> {
+            v.as_ref().map(|_| format_args!("<redacted>"))
+        }
+
+        f.debug_struct("Jwk")
+            .field("kty", &self.kty)
+            .field("use_", &self.use_)
+            .field("key_ops", &self.key_ops)
+            .field("alg", &self.alg)
+            .field("crv", &self.crv)
+            .field("kid", &self.kid)
+            .field("n", &self.n)
+            .field("e", &self.e)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("d", &redacted(&self.d))
+            .field("p", &redacted(&self.p))
+            .field("q", &redacted(&self.q))
+            .field("dp", &redacted(&self.dp))
+            .field("dq", &redacted(&self.dq))
+            .field("qi", &redacted(&self.qi))
+            .field("oth", &redacted(&(!self.oth.is_empty()).then_some(())))
+            .finish()
+    }
+}
+
 impl Jwk {
     pub fn to_verification_key(&self) -> Result<SomePublicKey> {
+        self.to_verification_key_with_policy(&KeyPolicy::default())
+    }
+
+    pub fn to_verification_key_with_policy(&self, policy: &KeyPolicy) -> Result<SomePublicKey> {
+        self.to_public_key(self.alg.as_deref(), policy)
+    }
+
+    /// Like [`to_verification_key`](Self::to_verification_key), but the key
+    /// only verifies `alg` signatures, whatever the `alg` of the JWK, e.g. to
+    /// correct a JWK with a wrong `alg`.
+    ///
+    /// Fails with [`Error::AlgorithmKeyMismatch`] if `alg` can't be used with
+    /// the key, e.g. `ES384` with a P-256 key.
+    pub fn to_verification_key_with_alg(&self, alg: Algorithm) -> Result<SomePublicKey> {
+        if alg.key_type() != self.kty {
+            return Err(Error::AlgorithmKeyMismatch);
+        }
+        let k = self.to_public_key(Some(alg.as_str()), &KeyPolicy::default())?;
+        if let SomePublicKey::Ecdsa(ec) = &k {
+            if Algorithm::from(ec.algorithm()) != alg {
+                return Err(Error::AlgorithmKeyMismatch);
+            }
+        }
+        Ok(k)
+    }
+
+    fn to_public_key(&self, alg: Option<&str>, policy: &KeyPolicy) -> Result<SomePublicKey> {
         // Check `use` and `key_ops`.
         if !matches!(self.use_.as_deref(), None | Some("sig")) {
             return Err(Error::UnsupportedOrInvalidKey);
@@ -78,30 +178,30 @@ impl Jwk {
         // If let would be too long.
         #[allow(clippy::single_match)]
         match &*self.kty {
-            "RSA" => match (self.alg.as_deref(), &self.n, &self.e) {
+            "RSA" => match (alg, &self.n, &self.e) {
                 (alg, Some(ref n), Some(ref e)) => {
-                    let n = URL_SAFE_TRAILING_BITS.decode(n)?;
-                    let e = URL_SAFE_TRAILING_BITS.decode(e)?;
+                    let n = decode_field(n)?;
+                    let e = decode_field(e)?;
                     // If `alg` is specified, the key will only verify
                     // signatures generated by ONLY this specific `alg`,
                     // otherwise it will verify signatures generated by ANY RSA
                     // algorithm.
                     let alg = if let Some(alg) = alg {
-                        Some(RsaAlgorithm::from_name(alg)?)
+                        Some(RsaAlgorithm::from_exact_name(alg)?)
                     } else {
                         None
                     };
-                    return Ok(SomePublicKey::Rsa(RsaPublicKey::from_components(
-                        &n, &e, alg,
-                    )?));
+                    return Ok(SomePublicKey::Rsa(
+                        RsaPublicKey::from_components_with_policy(&n, &e, alg, policy)?,
+                    ));
                 }
                 _ => {}
             },
             "EC" => match (self.crv.as_deref(), &self.x, &self.y) {
                 // For EC keys `crv` is required.
                 (Some(crv), Some(ref x), Some(ref y)) => {
-                    let x = URL_SAFE_TRAILING_BITS.decode(x)?;
-                    let y = URL_SAFE_TRAILING_BITS.decode(y)?;
+                    let x = decode_field(x)?;
+                    let y = decode_field(y)?;
                     let alg = EcdsaAlgorithm::from_curve_name(crv)?;
                     return Ok(SomePublicKey::Ecdsa(EcdsaPublicKey::from_coordinates(
                         &x, &y, alg,
@@ -110,10 +210,10 @@ impl Jwk {
                 _ => {}
             },
             "OKP" => match (self.crv.as_deref(), &self.x) {
-                (Some(crv), Some(ref x)) => {
-                    let x = URL_SAFE_TRAILING_BITS.decode(x)?;
+                (Some(crv), Some(ref x)) if matches!(alg, None | Some("EdDSA")) => {
+                    let x = decode_field(x)?;
                     match crv {
-                        "Ed25519" => {
+                        "Ed25519" if x.len() == 32 => {
                             return Ok(SomePublicKey::Ed25519(Ed25519PublicKey::from_bytes(&x)?));
                         }
                         _ => {}
@@ -132,14 +232,14 @@ impl Jwk {
         match &*self.kty {
             "RSA" => {
                 let alg = if let Some(ref alg) = self.alg {
-                    RsaAlgorithm::from_name(alg)?
+                    RsaAlgorithm::from_exact_name(alg)?
                 } else {
                     rsa_fallback_algorithm
                 };
                 match (self.d.as_deref(), self.n.as_deref(), self.e.as_deref()) {
                     (Some(d), Some(n), Some(e)) => {
                         fn decode(x: &str) -> Result<BigNum> {
-                            Ok(BigNum::from_slice(&URL_SAFE_TRAILING_BITS.decode(x)?)?)
+                            Ok(BigNum::from_slice(&decode_field(x)?)?)
                         }
                         let d = decode(d)?;
                         let n = decode(n)?;
@@ -153,9 +253,8 @@ impl Jwk {
                             self.oth.is_empty(),
                         ) {
                             (None, None, None, None, None, true) => {
-                                let rsa = RsaPrivateKeyBuilder::new(n, e, d)?.build();
-                                let pkey = PKey::from_rsa(rsa)?;
-                                RsaPrivateKey::from_pkey_without_check(pkey, alg).map(Into::into)
+                                RsaPrivateKey::from_n_e_d(n, e, d, alg, &KeyPolicy::default())
+                                    .map(Into::into)
                             }
                             (Some(p), Some(q), Some(dp), Some(dq), Some(qi), true) => {
                                 let p = decode(p)?;
@@ -165,7 +264,8 @@ impl Jwk {
                                 let qi = decode(qi)?;
                                 let rsa = Rsa::from_private_components(n, e, d, p, q, dp, dq, qi)?;
                                 let pkey = PKey::from_rsa(rsa)?;
-                                RsaPrivateKey::from_pkey(pkey, alg).map(Into::into)
+                                RsaPrivateKey::from_pkey(pkey, alg, &KeyPolicy::default())
+                                    .map(Into::into)
                             }
                             _ => Err(Error::UnsupportedOrInvalidKey),
                         }
@@ -182,18 +282,30 @@ impl Jwk {
                 ) {
                     (Some(crv), Some(d), Some(x), Some(y)) => {
                         let alg = EcdsaAlgorithm::from_curve_name(crv)?;
-                        let d = URL_SAFE_TRAILING_BITS.decode(d)?;
-                        let x = URL_SAFE_TRAILING_BITS.decode(x)?;
-                        let y = URL_SAFE_TRAILING_BITS.decode(y)?;
+                        let d = decode_field(d)?;
+                        let x = decode_field(x)?;
+                        let y = decode_field(y)?;
                         EcdsaPrivateKey::from_private_components(alg, &d, &x, &y).map(Into::into)
                     }
                     _ => Err(Error::UnsupportedOrInvalidKey),
                 }
             }
             "OKP" => match (self.crv.as_deref(), self.d.as_deref()) {
-                (Some("Ed25519"), Some(d)) => {
-                    let d = URL_SAFE_TRAILING_BITS.decode(d)?;
-                    Ed25519PrivateKey::from_bytes(&d).map(Into::into)
+                (Some("Ed25519"), Some(d))
+                    if matches!(self.alg.as_deref(), None | Some("EdDSA")) =>
+                {
+                    let d = decode_field(d)?;
+                    if d.len() != 32 {
+                        return Err(Error::UnsupportedOrInvalidKey);
+                    }
+                    let k = Ed25519PrivateKey::from_bytes(&d)?;
+                    // If the public key is present, it must match.
+                    if let Some(x) = self.x.as_deref() {
+                        if decode_field(x)? != k.public_key_bytes()? {
+                            return Err(Error::UnsupportedOrInvalidKey);
+                        }
+                    }
+                    Ok(k.into())
                 }
                 _ => Err(Error::UnsupportedOrInvalidKey),
             },
@@ -201,20 +313,35 @@ impl Jwk {
         }
     }
 
+    // This JWK without private key members.
+    fn public_members(&self) -> Jwk {
+        Jwk {
+            kty: self.kty.clone(),
+            use_: self.use_.clone(),
+            key_ops: self.key_ops.clone(),
+            alg: self.alg.clone(),
+            crv: self.crv.clone(),
+            kid: self.kid.clone(),
+            n: self.n.clone(),
+            e: self.e.clone(),
+            x: self.x.clone(),
+            y: self.y.clone(),
+            ..Default::default()
+        }
+    }
+
     /// Get key thumbprint (rfc 7638) with SHA-256.
     pub fn get_thumbprint_sha256(&self) -> Result<[u8; 32]> {
         let as_json = match &*self.kty {
             "RSA" => {
+                // Canonicalized, so that an `n` zero-padded by some other
+                // library has the same thumbprint.
+                let e = canonical_uint(self.e.as_deref().ok_or(Error::UnsupportedOrInvalidKey)?)?;
+                let n = canonical_uint(self.n.as_deref().ok_or(Error::UnsupportedOrInvalidKey)?)?;
                 let mut v = BTreeMap::new();
-                v.insert(
-                    "e",
-                    self.e.as_deref().ok_or(Error::UnsupportedOrInvalidKey)?,
-                );
+                v.insert("e", &*e);
                 v.insert("kty", "RSA");
-                v.insert(
-                    "n",
-                    self.n.as_deref().ok_or(Error::UnsupportedOrInvalidKey)?,
-                );
+                v.insert("n", &*n);
                 serde_json::to_string(&v)?
             }
             "EC" => {
@@ -261,22 +388,235 @@ impl Jwk {
     }
 }
 
+// The unparsed keys of a JWK Set, in either shape accepted by
+// `JwkSet::from_json`.
+fn key_values(v: Value, max_keys: usize) -> Result<Vec<Value>> {
+    let keys = match v {
+        Value::Array(keys) => keys,
+        Value::Object(mut m) => match m.remove("keys") {
+            Some(Value::Array(keys)) => keys,
+            _ => return Err(invalid_jwk_set_shape()),
+        },
+        _ => return Err(invalid_jwk_set_shape()),
+    };
+    if keys.len() > max_keys {
+        return Err(Error::TooManyKeys);
+    }
+    Ok(keys)
+}
+
+impl FromStr for Jwk {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+fn decode_header_of(token: &str) -> Result<Header> {
+    let header = token.split('.').next().ok_or(Error::InvalidToken)?;
+    decode_header(header.as_bytes())
+}
+
 /// JWK Set Representation.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JwkSet {
     pub keys: Vec<Jwk>,
 }
 
+/// Identifying members of a key in a [`JwkSet`], see
+/// [`JwkSet::key_summaries`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeySummary {
+    pub kid: Option<String>,
+    pub kty: String,
+    pub alg: Option<String>,
+    pub use_: Option<String>,
+}
+
 impl JwkSet {
+    /// Build a JWK Set publishing the public keys of `keys`, e.g. the current
+    /// and the next signing key.
+    ///
+    /// Keys without a `kid` get their thumbprint as `kid`, and `use` is set
+    /// to `sig`. The same key given more than once is only included once. Two
+    /// different keys with the same `kid` are an error.
+    pub fn from_signing_keys(keys: &[&dyn PublicKeyToJwk]) -> Result<Self> {
+        let mut set = JwkSet { keys: Vec::new() };
+        let mut thumbprints = Vec::new();
+        for k in keys {
+            let mut jwk = k.public_key_to_jwk()?;
+            let thumbprint = jwk.get_thumbprint_sha256()?;
+            if jwk.kid.is_none() {
+                jwk.kid = Some(URL_SAFE_TRAILING_BITS.encode(thumbprint));
+            }
+            jwk.use_ = Some("sig".into());
+            match set.keys.iter().position(|k| k.kid == jwk.kid) {
+                Some(i) if thumbprints[i] == thumbprint => continue,
+                Some(_) => return Err(Error::UnsupportedOrInvalidKey),
+                None => {}
+            }
+            set.keys.push(jwk);
+            thumbprints.push(thumbprint);
+        }
+        Ok(set)
+    }
+
+    /// Serialize this set for publishing, e.g. as a JWKS endpoint.
+    ///
+    /// Only signature keys, i.e. with `use` `sig` or no `use`, are included,
+    /// sorted by `kid`, and with only their public members. The output only
+    /// depends on the keys, not their order, so it's stable across runs and
+    /// can be diffed or used for an ETag.
+    pub fn to_published_json(&self, pretty: bool) -> Result<String> {
+        let mut keys = self
+            .keys
+            .iter()
+            .filter(|k| k.kty != "oct" && matches!(k.use_.as_deref(), None | Some("sig")))
+            .map(|k| {
+                let k = k.public_members();
+                // Breaks ties between keys with the same `kid` and `kty`.
+                let json = serde_json::to_string(&k)?;
+                Ok((k, json))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        keys.sort_by(|(a, a_json), (b, b_json)| {
+            (&a.kid, &a.kty, a_json).cmp(&(&b.kid, &b.kty, b_json))
+        });
+        let set = JwkSet {
+            keys: keys.into_iter().map(|(k, _)| k).collect(),
+        };
+        Ok(if pretty {
+            serde_json::to_string_pretty(&set)?
+        } else {
+            serde_json::to_string(&set)?
+        })
+    }
+
+    /// The `kid`, `kty`, `alg` and `use` of every key, in order, e.g. to
+    /// monitor which keys an issuer publishes.
+    ///
+    /// The key material isn't looked at, so this never fails. To also list
+    /// the keys of a set with entries that don't parse as JWKs, parse it with
+    /// [`from_json_lenient`](Self::from_json_lenient).
+    pub fn key_summaries(&self) -> Vec<KeySummary> {
+        self.keys
+            .iter()
+            .map(|k| KeySummary {
+                kid: k.kid.clone(),
+                kty: k.kty.clone(),
+                alg: k.alg.clone(),
+                use_: k.use_.clone(),
+            })
+            .collect()
+    }
+
+    /// The maximum number of keys accepted by [`from_json`](Self::from_json)
+    /// and friends.
+    pub const DEFAULT_MAX_KEYS: usize = 100;
+
+    /// Parse a JWK Set from JSON.
+    ///
+    /// Besides the standard `{"keys": [...]}` shape, a bare array of JWKs is
+    /// also accepted.
+    ///
+    /// Fails with [`Error::TooManyKeys`] if there are more than
+    /// [`DEFAULT_MAX_KEYS`](Self::DEFAULT_MAX_KEYS) keys.
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        Self::from_json_with_max_keys(json, Self::DEFAULT_MAX_KEYS)
+    }
+
+    /// Like [`from_json`](Self::from_json), but with a custom limit on the
+    /// number of keys.
+    pub fn from_json_with_max_keys(json: &[u8], max_keys: usize) -> Result<Self> {
+        Self::from_value(serde_json::from_slice(json)?, max_keys)
+    }
+
+    /// Parse a JWK Set from JSON, where the JWK Set is nested under `path`,
+    /// e.g. `&["jwks"]` for `{"jwks": {"keys": [...]}}`.
+    ///
+    /// The JWK Set itself may be either shape accepted by
+    /// [`from_json`](Self::from_json).
+    pub fn from_json_at(json: &[u8], path: &[&str]) -> Result<Self> {
+        let mut v: Value = serde_json::from_slice(json)?;
+        for field in path {
+            v = match v {
+                Value::Object(mut m) => m.remove(*field).ok_or_else(|| {
+                    Error::SerdeJson(serde::de::Error::custom(format!(
+                        "missing field `{}`",
+                        field
+                    )))
+                })?,
+                _ => return Err(invalid_jwk_set_shape()),
+            };
+        }
+        Self::from_value(v, Self::DEFAULT_MAX_KEYS)
+    }
+
+    /// Like [`from_json`](Self::from_json), but keys that fail to parse are
+    /// skipped instead of failing the whole set.
+    ///
+    /// Returns the index and error of every skipped key along with the set.
+    pub fn from_json_lenient(json: &[u8]) -> Result<(Self, Vec<(usize, Error)>)> {
+        let keys = key_values(serde_json::from_slice(json)?, Self::DEFAULT_MAX_KEYS)?;
+        let mut set = JwkSet { keys: Vec::new() };
+        let mut errors = Vec::new();
+        for (i, k) in keys.into_iter().enumerate() {
+            match serde_json::from_value(k) {
+                Ok(k) => set.keys.push(k),
+                Err(e) => errors.push((i, e.into())),
+            }
+        }
+        Ok((set, errors))
+    }
+
+    fn from_value(v: Value, max_keys: usize) -> Result<Self> {
+        Ok(JwkSet {
+            keys: serde_json::from_value(Value::Array(key_values(v, max_keys)?))?,
+        })
+    }
+
+    /// Check that every key in this set can be converted to a verification
+    /// key, without keeping the converted keys.
+    ///
+    /// Returns the index and error of every key that fails.
+    pub fn validate(&self) -> std::result::Result<(), Vec<(usize, Error)>> {
+        let errors: Vec<_> = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, k)| k.to_verification_key().err().map(|e| (i, e)))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn verifier(&self) -> JwkSetVerifier {
+        self.verifier_with_policy(&KeyPolicy::default())
+    }
+
+    /// Like [`verifier`](Self::verifier), but keys not allowed by `policy`
+    /// are skipped.
+    pub fn verifier_with_policy(&self, policy: &KeyPolicy) -> JwkSetVerifier {
         let mut prepared = JwkSetVerifier {
             keys: HashMap::new(),
             require_kid: true,
         };
         for k in self.keys.iter() {
             if let Some(ref kid) = k.kid {
-                if let Ok(vk) = k.to_verification_key() {
-                    prepared.keys.insert(kid.clone(), vk);
+                if let Ok(key) = k.to_verification_key_with_policy(policy) {
+                    prepared
+                        .keys
+                        .entry(kid.clone())
+                        .or_default()
+                        .push(PreparedKey {
+                            key,
+                            alg: k.alg.clone(),
+                        });
                 }
             }
         }
@@ -284,12 +624,46 @@ impl JwkSet {
     }
 }
 
+fn invalid_jwk_set_shape() -> Error {
+    Error::SerdeJson(serde::de::Error::custom(
+        r#"expected a JWK Set ({"keys": [...]}) or an array of JWKs"#,
+    ))
+}
+
+/// Same as [`JwkSet::from_json`].
+impl FromStr for JwkSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_json(s.as_bytes())
+    }
+}
+
 /// Jwk set parsed and converted, ready to verify tokens.
 pub struct JwkSetVerifier {
-    keys: HashMap<String, SomePublicKey>,
+    // Usually one key per `kid`, but some sets have keys with the same `kid`
+    // for different algorithms.
+    keys: HashMap<String, Vec<PreparedKey>>,
     require_kid: bool,
 }
 
+struct PreparedKey {
+    key: SomePublicKey,
+    // `alg` of the JWK.
+    alg: Option<String>,
+}
+
+impl PreparedKey {
+    // Whether this key may verify `alg` tokens. If the JWK has an `alg`, it
+    // must match. Otherwise, that's up to the key.
+    fn accepts(&self, alg: &str) -> bool {
+        match &self.alg {
+            Some(a) => a == alg,
+            None => self.key.accepted_algorithms().contains(&alg),
+        }
+    }
+}
+
 impl JwkSetVerifier {
     /// If called with `false`, subsequent `verify` and `verify_only` calls will
     /// try all keys from the key set if a `kid` is not specified in the token.
@@ -297,11 +671,42 @@ impl JwkSetVerifier {
         self.require_kid = required;
     }
 
+    /// Find a key by `kid`. If several keys have this `kid`, the first one is
+    /// returned, see [`find_with_alg`](Self::find_with_alg).
     pub fn find(&self, kid: &str) -> Option<&SomePublicKey> {
-        if let Some(vk) = self.keys.get(kid) {
-            Some(vk)
-        } else {
-            None
+        self.keys
+            .get(kid)
+            .and_then(|keys| keys.first())
+            .map(|k| &k.key)
+    }
+
+    // Number of keys.
+    #[cfg(feature = "remote-jwks")]
+    fn len(&self) -> usize {
+        self.keys.values().map(Vec::len).sum()
+    }
+
+    /// Find the key for a token with `kid` and `alg`.
+    ///
+    /// If only one key has this `kid`, it is returned, unless its JWK has an
+    /// `alg` other than `alg`, then [`Error::AlgorithmKeyMismatch`] is
+    /// returned. Otherwise, the key that accepts `alg` is returned, or
+    /// [`Error::AmbiguousKey`] if there are several.
+    pub fn find_with_alg(&self, kid: &str, alg: &str) -> Result<&SomePublicKey> {
+        match self.keys.get(kid).map(Vec::as_slice) {
+            None | Some([]) => Err(Error::NoKey),
+            Some([k]) if k.alg.as_ref().is_some_and(|a| a != alg) => {
+                Err(Error::AlgorithmKeyMismatch)
+            }
+            Some([k]) => Ok(&k.key),
+            Some(keys) => {
+                let mut matching = keys.iter().filter(|k| k.accepts(alg)).map(|k| &k.key);
+                match (matching.next(), matching.next()) {
+                    (Some(k), None) => Ok(k),
+                    (Some(_), Some(_)) => Err(Error::AmbiguousKey),
+                    (None, _) => Err(Error::NoKey),
+                }
+            }
         }
     }
 
@@ -312,7 +717,37 @@ impl JwkSetVerifier {
         &self,
         token: &str,
     ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        self.find_and_verify(token, verify)
+        self.find_and_verify(token, &VerifyOptions::default(), verify)
+            .map(|(verified, _)| verified)
+    }
+
+    /// Decode and verify token with keys from this JWK set, then validate
+    /// claims according to `options`.
+    pub fn verify_with_options<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &VerifyOptions,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        self.verify_with_options_and_kid(token, options)
+            .map(|(verified, _)| verified)
+    }
+
+    /// Like [`verify_with_options`](Self::verify_with_options), but also
+    /// returns the `kid` of the key that verified the token.
+    ///
+    /// Without [`set_require_kid`](Self::set_require_kid), that's the `kid` of
+    /// the token. Otherwise, a token without `kid` is verified by trying all
+    /// keys, and this tells which one matched.
+    pub fn verify_with_options_and_kid<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &VerifyOptions,
+    ) -> Result<(HeaderAndClaims<ExtraClaims>, String)> {
+        options.check_len(token.as_bytes())?;
+        self.find_and_verify(token, options, |token, k| {
+            verify_with_options(token, k, options)
+        })
+        .map(|(verified, kid)| (verified, kid.into()))
     }
 
     /// Decode and verify token with keys from this JWK set. Won't check `exp` and `nbf`.
@@ -320,44 +755,73 @@ impl JwkSetVerifier {
         &self,
         token: &str,
     ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        self.find_and_verify(token, verify_only)
+        self.find_and_verify(token, &VerifyOptions::default(), verify_only)
+            .map(|(verified, _)| verified)
     }
 
     /// Find and verify token with keys from this JWK set.
     ///
-    /// restrict_kid is true will only match keys with the same `kid`.
+    /// Returns the `kid` of the key that verified the token along with the
+    /// token.
     fn find_and_verify<ExtraClaims: DeserializeOwned>(
         &self,
         token: &str,
-        verifier: fn(&str, &dyn VerificationKey) -> Result<HeaderAndClaims<ExtraClaims>>,
-    ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        let mut parts = token.split('.');
-
-        let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-
-        let header_r = base64::read::DecoderReader::new(&mut header, &URL_SAFE_TRAILING_BITS);
-        let header: Header = serde_json::from_reader(header_r)?;
+        options: &VerifyOptions,
+        verifier: impl Fn(&str, &dyn VerificationKey) -> Result<HeaderAndClaims<ExtraClaims>>,
+    ) -> Result<(HeaderAndClaims<ExtraClaims>, &str)> {
+        let mut header = decode_header_of(token)?;
+        options.check_kid(&header)?;
+        options.normalize_alg(&mut header);
 
         if let Some(kid) = header.kid {
-            let k = self.find(&kid).ok_or(Error::NoKey)?;
-            verifier(token, k)
+            let (kid, _) = self.keys.get_key_value(&kid).ok_or(Error::NoKey)?;
+            let k = self.find_with_alg(kid, &header.alg)?;
+            Ok((verifier(token, k)?, kid))
         } else if !self.require_kid {
-            if let Some(res) = self
-                .keys
-                .values()
-                .map(|key| verifier(token, key))
-                .find_map(|res| res.ok())
-            {
-                Ok(res)
-            } else {
-                Err(Error::NoKey)
-            }
+            self.keys
+                .iter()
+                .flat_map(|(kid, keys)| keys.iter().map(move |k| (kid, k)))
+                .filter(|(_, k)| k.alg.is_none() || k.accepts(&header.alg))
+                .find_map(|(kid, k)| Some((verifier(token, &k.key).ok()?, &kid[..])))
+                .ok_or(Error::NoKey)
         } else {
             Err(Error::NoKey)
         }
     }
 }
 
+macro_rules! impl_try_from_jwk {
+    ($convert:expr, $some:ident, $($variant:ident => $key:ty),*) => {$(
+        impl TryFrom<&Jwk> for $key {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self> {
+                match $convert(jwk)? {
+                    $some::$variant(k) => Ok(k),
+                    _ => Err(Error::UnsupportedOrInvalidKey),
+                }
+            }
+        }
+    )*};
+}
+
+impl_try_from_jwk!(
+    Jwk::to_verification_key,
+    SomePublicKey,
+    Rsa => RsaPublicKey,
+    Ecdsa => EcdsaPublicKey,
+    Ed25519 => Ed25519PublicKey
+);
+
+// RSA private JWKs without `alg` use RS256, like `to_signing_key(RS256)`.
+impl_try_from_jwk!(
+    |jwk: &Jwk| jwk.to_signing_key(RsaAlgorithm::RS256),
+    SomePrivateKey,
+    Rsa => RsaPrivateKey,
+    Ecdsa => EcdsaPrivateKey,
+    Ed25519 => Ed25519PrivateKey
+);
+
 /// A key associated with a key id (`kid`).
 ///
 /// When the key is used for signing, `kid` is automatically set.
@@ -422,6 +886,18 @@ impl<S: VerificationKey> VerificationKey for WithKid<S> {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
         self.inner.verify(v, sig, alg)
     }
+
+    fn verify_reader(&self, input: &mut dyn std::io::Read, sig: &[u8], alg: &str) -> Result<()> {
+        self.inner.verify_reader(input, sig, alg)
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        self.inner.accepted_algorithms()
+    }
+
+    fn can_sign(&self) -> bool {
+        self.inner.can_sign()
+    }
 }
 
 impl<K: PublicKeyToJwk> PublicKeyToJwk for WithKid<K> {
@@ -432,22 +908,172 @@ impl<K: PublicKeyToJwk> PublicKeyToJwk for WithKid<K> {
     }
 }
 
+/// Observer of [`RemoteJwksVerifier`] events, e.g. for metrics.
+///
+/// All methods do nothing by default.
+#[cfg(feature = "remote-jwks")]
+pub trait JwksObserver: Send + Sync {
+    /// A JWK Set fetch is about to start.
+    fn on_fetch_start(&self) {}
+
+    /// A JWK Set fetch succeeded and the key set contained `key_count` keys.
+    fn on_fetch_success(&self, _elapsed: std::time::Duration, _key_count: usize) {}
+
+    /// A JWK Set fetch failed.
+    fn on_fetch_failure(&self, _elapsed: std::time::Duration, _error: &Error) {}
+
+    /// A still valid cached key set was used.
+    fn on_cache_hit(&self) {}
+
+    /// The cached key set was replaced with a newly fetched one, which has
+    /// `key_count` usable keys.
+    fn on_cache_swap(&self, _key_count: usize) {}
+}
+
 #[cfg(feature = "remote-jwks")]
 struct JWKSCache {
     jwks: JwkSetVerifier,
+    fetched_at: std::time::Instant,
     valid_until: std::time::Instant,
 }
 
 /// A JWK Set served from a remote url. Automatically fetched and cached.
+///
+/// If a refresh fails and a previously fetched key set is cached, the stale
+/// key set keeps being used until a later refresh succeeds. Refreshes are
+/// then retried at most every
+/// [`retry_interval`](RemoteJwksVerifierBuilder::retry_interval).
+///
+/// Only one fetch is in flight at a time. Requests that arrive while the key
+/// set is being fetched for the first time wait for that fetch, and fail with
+/// [`Error::JwksUnavailable`] if it fails. Call
+/// [`initialize`](Self::initialize) at startup to fetch the key set before the
+/// first request.
 #[cfg(feature = "remote-jwks")]
 pub struct RemoteJwksVerifier {
     url: String,
     client: reqwest::Client,
     cache_duration: std::time::Duration,
+    request_timeout: std::time::Duration,
+    observer: Option<std::sync::Arc<dyn JwksObserver>>,
+    request_decorator: Option<RequestDecorator>,
+    max_keys: usize,
+    min_refresh_interval: std::time::Duration,
+    retry_interval: std::time::Duration,
     cache: tokio::sync::RwLock<Option<JWKSCache>>,
+    // Number of failed fetches while nothing was cached.
+    initial_fetch_failures: std::sync::atomic::AtomicUsize,
+    // Number of key sets stored, so that concurrent unknown kid refreshes can
+    // tell that another one already happened.
+    generation: std::sync::atomic::AtomicUsize,
+    require_kid: bool,
+    // Verifiers for key sets at allowlisted `jku`s, by url.
+    jku_verifiers: std::sync::Mutex<HashMap<String, std::sync::Arc<RemoteJwksVerifier>>>,
+}
+
+/// See [`RemoteJwksVerifierBuilder::request_decorator`].
+#[cfg(feature = "remote-jwks")]
+pub type RequestDecorator =
+    std::sync::Arc<dyn Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder + Send + Sync>;
+
+/// Builder for [`RemoteJwksVerifier`].
+#[cfg(feature = "remote-jwks")]
+pub struct RemoteJwksVerifierBuilder {
+    url: String,
+    client: Option<reqwest::Client>,
+    cache_duration: std::time::Duration,
+    request_timeout: std::time::Duration,
+    observer: Option<std::sync::Arc<dyn JwksObserver>>,
+    request_decorator: Option<RequestDecorator>,
+    max_keys: usize,
+    min_refresh_interval: std::time::Duration,
+    retry_interval: std::time::Duration,
     require_kid: bool,
 }
 
+#[cfg(feature = "remote-jwks")]
+impl RemoteJwksVerifierBuilder {
+    /// Use this client for fetching the JWK Set. Defaults to
+    /// `reqwest::Client::default()`.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// How long a fetched JWK Set is cached. Defaults to 5 minutes.
+    pub fn cache_duration(mut self, cache_duration: std::time::Duration) -> Self {
+        self.cache_duration = cache_duration;
+        self
+    }
+
+    /// Timeout of each JWK Set request, from connecting until the response
+    /// body has been read. Defaults to 10 seconds.
+    pub fn request_timeout(mut self, request_timeout: std::time::Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Notify this observer of fetches and cache usage.
+    pub fn observer(mut self, observer: std::sync::Arc<dyn JwksObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Apply `decorator` to each JWK Set request before it is sent, e.g. to
+    /// add an `Authorization` header.
+    pub fn request_decorator(mut self, decorator: RequestDecorator) -> Self {
+        self.request_decorator = Some(decorator);
+        self
+    }
+
+    /// Reject JWK Sets with more than `max_keys` keys with
+    /// [`Error::TooManyKeys`]. Defaults to [`JwkSet::DEFAULT_MAX_KEYS`].
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Minimum time between refetches triggered by tokens with an unknown
+    /// `kid`, see [`RemoteJwksVerifier::verify_with_options`]. Defaults to 30
+    /// seconds.
+    pub fn min_refresh_interval(mut self, interval: std::time::Duration) -> Self {
+        self.min_refresh_interval = interval;
+        self
+    }
+
+    /// How long a stale key set is used after a refresh fails, before the
+    /// next refresh is tried. Defaults to 30 seconds.
+    pub fn retry_interval(mut self, interval: std::time::Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// See [`RemoteJwksVerifier::set_require_kid`]. Defaults to `true`.
+    pub fn require_kid(mut self, required: bool) -> Self {
+        self.require_kid = required;
+        self
+    }
+
+    pub fn build(self) -> RemoteJwksVerifier {
+        RemoteJwksVerifier {
+            url: self.url,
+            client: self.client.unwrap_or_default(),
+            cache_duration: self.cache_duration,
+            request_timeout: self.request_timeout,
+            observer: self.observer,
+            request_decorator: self.request_decorator,
+            max_keys: self.max_keys,
+            min_refresh_interval: self.min_refresh_interval,
+            retry_interval: self.retry_interval,
+            cache: tokio::sync::RwLock::new(None),
+            initial_fetch_failures: Default::default(),
+            generation: Default::default(),
+            require_kid: self.require_kid,
+            jku_verifiers: Default::default(),
+        }
+    }
+}
+
 #[cfg(feature = "remote-jwks")]
 impl RemoteJwksVerifier {
     pub fn new(
@@ -455,11 +1081,22 @@ impl RemoteJwksVerifier {
         client: Option<reqwest::Client>,
         cache_duration: std::time::Duration,
     ) -> Self {
-        Self {
+        let mut builder = Self::builder(url).cache_duration(cache_duration);
+        builder.client = client;
+        builder.build()
+    }
+
+    pub fn builder(url: String) -> RemoteJwksVerifierBuilder {
+        RemoteJwksVerifierBuilder {
             url,
-            client: client.unwrap_or_default(),
-            cache_duration,
-            cache: tokio::sync::RwLock::new(None),
+            client: None,
+            cache_duration: std::time::Duration::from_secs(300),
+            request_timeout: std::time::Duration::from_secs(10),
+            observer: None,
+            request_decorator: None,
+            max_keys: JwkSet::DEFAULT_MAX_KEYS,
+            min_refresh_interval: std::time::Duration::from_secs(30),
+            retry_interval: std::time::Duration::from_secs(30),
             require_kid: true,
         }
     }
@@ -471,9 +1108,22 @@ impl RemoteJwksVerifier {
         if let Some(ref mut v) = self.cache.get_mut() {
             v.jwks.require_kid = required;
         }
+        // Rebuilt with the new setting when next used.
+        self.jku_verifiers
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Fetch the key set now, if it is not cached yet.
+    pub async fn initialize(&self) -> Result<()> {
+        self.get_verifier().await.map(drop)
     }
 
     async fn get_verifier(&self) -> Result<tokio::sync::RwLockReadGuard<'_, JwkSetVerifier>> {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        let initial_fetch_failures = self.initial_fetch_failures.load(Relaxed);
         let cache = self.cache.read().await;
         // Cache still valid.
         if let Some(c) = &*cache {
@@ -481,6 +1131,9 @@ impl RemoteJwksVerifier {
                 .checked_duration_since(std::time::Instant::now())
                 .is_some()
             {
+                if let Some(o) = &self.observer {
+                    o.on_cache_hit();
+                }
                 return Ok(tokio::sync::RwLockReadGuard::map(cache, |c| {
                     &c.as_ref().unwrap().jwks
                 }));
@@ -494,33 +1147,104 @@ impl RemoteJwksVerifier {
                 .checked_duration_since(std::time::Instant::now())
                 .is_some()
             {
+                if let Some(o) = &self.observer {
+                    o.on_cache_hit();
+                }
                 return Ok(tokio::sync::RwLockReadGuard::map(cache.downgrade(), |c| {
                     &c.as_ref().unwrap().jwks
                 }));
             }
         }
-        let response = self
-            .client
-            .get(&self.url)
-            .header("accept", "application/json")
-            .send()
-            .await?;
-        let jwks: JwkSet = response.json().await?;
+        // The first fetch failed while we were waiting for it, don't retry
+        // right away.
+        if cache.is_none() && self.initial_fetch_failures.load(Relaxed) != initial_fetch_failures {
+            return Err(Error::JwksUnavailable);
+        }
+        let jwks = match self.fetch().await {
+            Ok(jwks) => jwks,
+            // Keep using the stale key set, if there is one, and don't refetch
+            // on every request while the endpoint is down.
+            Err(_) if cache.is_some() => {
+                if let Some(c) = &mut *cache {
+                    c.valid_until = std::time::Instant::now() + self.retry_interval;
+                }
+                return Ok(tokio::sync::RwLockReadGuard::map(cache.downgrade(), |c| {
+                    &c.as_ref().unwrap().jwks
+                }));
+            }
+            Err(e) => {
+                self.initial_fetch_failures.fetch_add(1, Relaxed);
+                return Err(e);
+            }
+        };
 
-        *cache = Some(JWKSCache {
-            jwks: {
-                let mut v = jwks.verifier();
-                v.require_kid = self.require_kid;
-                v
-            },
-            valid_until: std::time::Instant::now() + self.cache_duration,
-        });
+        self.store(&mut cache, jwks);
 
         Ok(tokio::sync::RwLockReadGuard::map(cache.downgrade(), |c| {
             &c.as_ref().unwrap().jwks
         }))
     }
 
+    fn store(&self, cache: &mut Option<JWKSCache>, jwks: JwkSet) {
+        let mut jwks = jwks.verifier();
+        jwks.require_kid = self.require_kid;
+        if let Some(o) = &self.observer {
+            o.on_cache_swap(jwks.len());
+        }
+        self.generation
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let now = std::time::Instant::now();
+        *cache = Some(JWKSCache {
+            jwks,
+            fetched_at: now,
+            valid_until: now + self.cache_duration,
+        });
+    }
+
+    // Refetch the key set, unless it was fetched recently, or was replaced
+    // since `generation`, e.g. by a concurrent refresh for the same new kid.
+    // Errors are ignored, the stale key set is kept.
+    async fn refresh_for_unknown_kid(&self, generation: usize) {
+        let mut cache = self.cache.write().await;
+        if self.generation.load(std::sync::atomic::Ordering::Relaxed) != generation
+            || cache
+                .as_ref()
+                .is_some_and(|c| c.fetched_at.elapsed() < self.min_refresh_interval)
+        {
+            return;
+        }
+        if let Ok(jwks) = self.fetch().await {
+            self.store(&mut cache, jwks);
+        }
+    }
+
+    async fn fetch(&self) -> Result<JwkSet> {
+        let Some(o) = &self.observer else {
+            return self.do_fetch().await;
+        };
+        o.on_fetch_start();
+        let start = std::time::Instant::now();
+        let res = self.do_fetch().await;
+        match &res {
+            Ok(jwks) => o.on_fetch_success(start.elapsed(), jwks.keys.len()),
+            Err(e) => o.on_fetch_failure(start.elapsed(), e),
+        }
+        res
+    }
+
+    async fn do_fetch(&self) -> Result<JwkSet> {
+        let mut request = self
+            .client
+            .get(&self.url)
+            .header("accept", "application/json")
+            .timeout(self.request_timeout);
+        if let Some(decorate) = &self.request_decorator {
+            request = decorate(request);
+        }
+        let response = request.send().await?.error_for_status()?;
+        JwkSet::from_json_with_max_keys(&response.bytes().await?, self.max_keys)
+    }
+
     pub async fn verify<E: DeserializeOwned>(&self, token: &str) -> Result<HeaderAndClaims<E>> {
         let v = self.get_verifier().await?;
         v.verify(token)
@@ -533,6 +1257,173 @@ impl RemoteJwksVerifier {
         let v = self.get_verifier().await?;
         v.verify_only(token)
     }
+
+    /// Verify `token` with the key matching its `kid`, then validate claims
+    /// according to `options`.
+    ///
+    /// If no key matches the `kid`, the key set is refetched once, in case the
+    /// key was added since it was cached. To bound the load on the JWKS
+    /// endpoint, this happens at most once per
+    /// [`min_refresh_interval`](RemoteJwksVerifierBuilder::min_refresh_interval),
+    /// and concurrent verifications that miss the same key set share one
+    /// refetch: they wait for it, and fail with [`Error::NoKey`] only if the
+    /// `kid` is still missing afterwards.
+    ///
+    /// If the token has a `jku` allowed by
+    /// [`VerifyOptions::set_allowed_jku`], the key set at that url is used
+    /// instead. It is fetched and cached like the key set of this verifier,
+    /// but without the [`request_decorator`](RemoteJwksVerifierBuilder::request_decorator),
+    /// so credentials for this endpoint don't leak to others.
+    pub async fn verify_with_options<E: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &VerifyOptions,
+    ) -> Result<HeaderAndClaims<E>> {
+        self.verify_with_options_and_kid(token, options)
+            .await
+            .map(|(verified, _)| verified)
+    }
+
+    /// Like [`verify_with_options`](Self::verify_with_options), but also
+    /// returns the `kid` of the key that verified the token, see
+    /// [`JwkSetVerifier::verify_with_options_and_kid`].
+    pub async fn verify_with_options_and_kid<E: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &VerifyOptions,
+    ) -> Result<(HeaderAndClaims<E>, String)> {
+        options.check_len(token.as_bytes())?;
+        let header = decode_header_of(token)?;
+        match options.check_jku(&header)? {
+            Some(jku) if jku != self.url => {
+                let v = self.jku_verifier(jku);
+                v.verify_with_own_key_set(token, options).await
+            }
+            _ => self.verify_with_own_key_set(token, options).await,
+        }
+    }
+
+    fn jku_verifier(&self, jku: &str) -> std::sync::Arc<RemoteJwksVerifier> {
+        let mut verifiers = self.jku_verifiers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(v) = verifiers.get(jku) {
+            return v.clone();
+        }
+        let mut builder = Self::builder(jku.into())
+            .client(self.client.clone())
+            .cache_duration(self.cache_duration)
+            .request_timeout(self.request_timeout)
+            .max_keys(self.max_keys)
+            .min_refresh_interval(self.min_refresh_interval)
+            .retry_interval(self.retry_interval)
+            .require_kid(self.require_kid);
+        builder.observer = self.observer.clone();
+        let v = std::sync::Arc::new(builder.build());
+        verifiers.insert(jku.into(), v.clone());
+        v
+    }
+
+    async fn verify_with_own_key_set<E: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &VerifyOptions,
+    ) -> Result<(HeaderAndClaims<E>, String)> {
+        let generation = self.generation.load(std::sync::atomic::Ordering::Relaxed);
+        match self
+            .get_verifier()
+            .await?
+            .verify_with_options_and_kid(token, options)
+        {
+            Err(Error::NoKey) if decode_header_of(token)?.kid.is_some() => {}
+            res => return res,
+        }
+        self.refresh_for_unknown_kid(generation).await;
+        self.get_verifier()
+            .await?
+            .verify_with_options_and_kid(token, options)
+    }
+}
+
+/// Verifies tokens from several issuers, each with its own JWK Set and
+/// options, by the token's `iss`.
+///
+/// Issuers can be added and removed while the verifier is in use.
+#[cfg(feature = "remote-jwks")]
+#[derive(Default)]
+pub struct MultiIssuerVerifier {
+    issuers: std::sync::RwLock<HashMap<String, std::sync::Arc<IssuerVerifier>>>,
+}
+
+#[cfg(feature = "remote-jwks")]
+struct IssuerVerifier {
+    verifier: RemoteJwksVerifier,
+    options: VerifyOptions,
+}
+
+#[cfg(feature = "remote-jwks")]
+impl MultiIssuerVerifier {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify tokens with `iss` equal to `issuer` with `verifier` and
+    /// `options`. Replaces the previous verifier of `issuer`, if any.
+    ///
+    /// The issuer of `options` is set to `issuer`.
+    pub fn add_issuer(
+        &self,
+        issuer: impl Into<String>,
+        verifier: RemoteJwksVerifier,
+        mut options: VerifyOptions,
+    ) {
+        let issuer = issuer.into();
+        options.set_issuer(issuer.clone());
+        self.issuers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                issuer,
+                std::sync::Arc::new(IssuerVerifier { verifier, options }),
+            );
+    }
+
+    /// Returns whether `issuer` was known.
+    pub fn remove_issuer(&self, issuer: &str) -> bool {
+        self.issuers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(issuer)
+            .is_some()
+    }
+
+    /// Verify `token` with the verifier and options of its `iss`, see
+    /// [`RemoteJwksVerifier::verify_with_options`].
+    ///
+    /// Fails with [`Error::InvalidIssuer`] if the issuer is unknown, and with
+    /// [`Error::MissingClaim`] if the token has no `iss`.
+    pub async fn verify<E: DeserializeOwned>(&self, token: &str) -> Result<HeaderAndClaims<E>> {
+        let issuer = {
+            let issuers = self.issuers.read().unwrap_or_else(|e| e.into_inner());
+            // The token isn't verified yet, so bound the work done to route it
+            // by the largest limit of any issuer. The limit of its issuer is
+            // checked when verifying.
+            let max_len = issuers
+                .values()
+                .map(|issuer| issuer.options.token_len_limit())
+                .max()
+                .unwrap_or(VerifyOptions::DEFAULT_MAX_TOKEN_LEN);
+            if token.len() > max_len {
+                return Err(Error::MalformedToken);
+            }
+            let iss: String = crate::unverified_claim(token.as_bytes(), "iss")?
+                .ok_or(Error::MissingClaim("iss"))?;
+            issuers.get(&iss).cloned().ok_or(Error::InvalidIssuer)?
+        };
+        issuer
+            .verifier
+            .verify_with_options(token, &issuer.options)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -541,7 +1432,7 @@ mod tests {
         ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
         eddsa::Ed25519PrivateKey,
         rsa::RsaPrivateKey,
-        sign,
+        sign, PrivateKeyToJwk,
     };
 
     use super::*;
@@ -566,6 +1457,399 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwk_padded_fields() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let mut jwk = k.public_key_to_jwk()?;
+        // Standard alphabet, and 256 bytes so padded with `==`.
+        let n = base64::engine::general_purpose::STANDARD.encode(k.n()?);
+        assert!(n.ends_with("=="));
+        jwk.n = Some(n);
+        let pk = jwk.to_verification_key()?;
+        let sig = k.sign(b"...")?;
+        pk.verify(b"...", &sig, "RS256")?;
+
+        jwk.n = Some("not base64!".into());
+        assert!(jwk.to_verification_key().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_zero_padded_modulus() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let canonical = k.public_key_to_jwk()?;
+        let mut padded_n = vec![0];
+        padded_n.extend_from_slice(&k.n()?);
+        let mut padded = k.public_key_to_jwk()?;
+        padded.n = Some(URL_SAFE_TRAILING_BITS.encode(&padded_n));
+        assert_ne!(padded.n, canonical.n);
+
+        assert_eq!(
+            padded.get_thumbprint_sha256()?,
+            canonical.get_thumbprint_sha256()?
+        );
+        let round_tripped = RsaPublicKey::from_components(&padded_n, &k.e()?, None)?;
+        assert_eq!(round_tripped.public_key_to_jwk()?.n, canonical.n);
+        let SomePublicKey::Rsa(from_jwk) = padded.to_verification_key()? else {
+            panic!("not an RSA key");
+        };
+        assert_eq!(from_jwk.n()?, k.n()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_alg_override() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        let mut jwk = k.public_key_to_jwk()?;
+        jwk.alg = Some("RS256".into());
+        let sig = k.sign(b"...")?;
+        assert!(jwk
+            .to_verification_key()?
+            .verify(b"...", &sig, "PS256")
+            .is_err());
+        let pk = jwk.to_verification_key_with_alg(Algorithm::PS256)?;
+        pk.verify(b"...", &sig, "PS256")?;
+        assert!(pk.verify(b"...", &sig, "PS384").is_err());
+        assert!(matches!(
+            jwk.to_verification_key_with_alg(Algorithm::ES256),
+            Err(Error::AlgorithmKeyMismatch)
+        ));
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut jwk = k.public_key_to_jwk()?;
+        jwk.alg = None;
+        jwk.to_verification_key_with_alg(Algorithm::ES256)?;
+        assert!(matches!(
+            jwk.to_verification_key_with_alg(Algorithm::ES384),
+            Err(Error::AlgorithmKeyMismatch)
+        ));
+
+        let mut jwk = Ed25519PrivateKey::generate()?.public_key_to_jwk()?;
+        jwk.alg = Some("ES256".into());
+        assert!(jwk.to_verification_key().is_err());
+        jwk.to_verification_key_with_alg(Algorithm::EdDSA)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_from_jwk() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?;
+        let public = k.public_key_to_jwk()?;
+        let private = k.private_key_to_jwk()?;
+        assert_eq!(
+            EcdsaPublicKey::try_from(&public)?.accepted_algorithms(),
+            ["ES384"]
+        );
+        assert!(EcdsaPrivateKey::try_from(&private).is_ok());
+        assert!(EcdsaPrivateKey::try_from(&public).is_err());
+        assert!(matches!(
+            RsaPublicKey::try_from(&public),
+            Err(Error::UnsupportedOrInvalidKey)
+        ));
+        assert!(Ed25519PublicKey::try_from(&public).is_err());
+
+        let k = Ed25519PrivateKey::generate()?;
+        assert!(Ed25519PublicKey::try_from(&k.public_key_to_jwk()?).is_ok());
+        assert!(Ed25519PrivateKey::try_from(&k.private_key_to_jwk()?).is_ok());
+        assert!(RsaPrivateKey::try_from(&k.private_key_to_jwk()?).is_err());
+
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        assert_eq!(
+            RsaPrivateKey::try_from(&k.private_key_to_jwk()?)?.algorithm,
+            RsaAlgorithm::PS256
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_kid_types() -> Result<()> {
+        let json = br#"{"keys": [
+            {"kty": "OKP", "kid": 1},
+            {"kty": "OKP", "kid": "two"},
+            {"kty": "OKP", "kid": {"bad": true}},
+            {"kty": "OKP"}
+        ]}"#;
+        assert!(JwkSet::from_json(json).is_err());
+
+        let (set, errors) = JwkSet::from_json_lenient(json)?;
+        let kids: Vec<_> = set.keys.iter().map(|k| k.kid.as_deref()).collect();
+        assert_eq!(kids, [Some("1"), Some("two"), None]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 2);
+
+        let set = JwkSet::from_json(br#"[{"kty": "OKP", "kid": 1}]"#)?;
+        assert_eq!(set.keys[0].kid.as_deref(), Some("1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_from_signing_keys() -> Result<()> {
+        let current = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let next = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let set = JwkSet::from_signing_keys(&[&current, &next, &current])?;
+        assert_eq!(set.keys.len(), 2);
+        assert_ne!(set.keys[0].kid, set.keys[1].kid);
+        assert!(set.keys.iter().all(|k| k.use_.as_deref() == Some("sig")));
+        assert_eq!(
+            set.keys[0].kid.as_deref(),
+            Some(
+                &*current
+                    .public_key_to_jwk()?
+                    .get_thumbprint_sha256_base64()?
+            )
+        );
+
+        let token = sign(
+            &mut HeaderAndClaims::new_dynamic(),
+            &WithKid::new(set.keys[1].kid.clone().unwrap(), next),
+        )?;
+        set.verifier().verify::<Value>(&token)?;
+
+        let a = WithKid::new("same".into(), Ed25519PrivateKey::generate()?);
+        let b = WithKid::new("same".into(), Ed25519PrivateKey::generate()?);
+        assert!(JwkSet::from_signing_keys(&[&a, &b]).is_err());
+        assert_eq!(JwkSet::from_signing_keys(&[&a, &a])?.keys.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_redacts_private_members() -> Result<()> {
+        let jwk = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?.private_key_to_jwk()?;
+        let debug = format!("{:?}", jwk);
+        for private in [&jwk.d, &jwk.p, &jwk.q, &jwk.dp, &jwk.dq, &jwk.qi] {
+            assert!(!debug.contains(private.as_deref().unwrap()));
+        }
+        assert!(debug.contains(jwk.n.as_deref().unwrap()));
+        assert!(debug.contains(r#"d: Some(<redacted>)"#));
+
+        let jwk = Ed25519PrivateKey::generate()?.public_key_to_jwk()?;
+        assert!(format!("{:?}", jwk).contains("d: None"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_summaries() -> Result<()> {
+        let json = br#"{"keys": [
+            {"kty": "RSA", "kid": "a", "alg": "RS256", "use": "sig", "n": "!", "e": "AQAB"},
+            {"kid": "b"},
+            {"kty": "OKP", "kid": 7}
+        ]}"#;
+        let (set, errors) = JwkSet::from_json_lenient(json)?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            set.key_summaries(),
+            [
+                KeySummary {
+                    kid: Some("a".into()),
+                    kty: "RSA".into(),
+                    alg: Some("RS256".into()),
+                    use_: Some("sig".into()),
+                },
+                KeySummary {
+                    kid: Some("7".into()),
+                    kty: "OKP".into(),
+                    alg: None,
+                    use_: None,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_to_published_json() -> Result<()> {
+        let rsa = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let mut b = rsa.private_key_to_jwk()?;
+        b.kid = Some("b".into());
+        let mut a = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.private_key_to_jwk()?;
+        a.kid = Some("a".into());
+        a.use_ = None;
+        let mut enc = Ed25519PrivateKey::generate()?.public_key_to_jwk()?;
+        enc.kid = Some("0".into());
+        enc.use_ = Some("enc".into());
+        let set = JwkSet {
+            keys: vec![b, enc, a],
+        };
+
+        let json = set.to_published_json(false)?;
+        let published = JwkSet::from_json(json.as_bytes())?;
+        let kids: Vec<_> = published.keys.iter().map(|k| k.kid.as_deref()).collect();
+        assert_eq!(kids, [Some("a"), Some("b")]);
+        assert!(published
+            .keys
+            .iter()
+            .all(|k| k.d.is_none() && k.p.is_none()));
+        assert!(!json.contains("null"));
+        assert!(!json.contains('\n'));
+        assert_eq!(
+            published.keys[1].get_thumbprint_sha256()?,
+            rsa.public_key_to_jwk()?.get_thumbprint_sha256()?
+        );
+
+        let reversed = JwkSet {
+            keys: set.keys.into_iter().rev().collect(),
+        };
+        assert_eq!(reversed.to_published_json(false)?, json);
+
+        // Keys with the same `kid` and `kty` are ordered too.
+        let same = |n: u8| -> Result<Jwk> {
+            let mut k = Ed25519PrivateKey::from_bytes(&[n; 32])?.public_key_to_jwk()?;
+            k.kid = Some("same".into());
+            Ok(k)
+        };
+        assert_eq!(
+            JwkSet {
+                keys: vec![same(1)?, same(2)?]
+            }
+            .to_published_json(false)?,
+            JwkSet {
+                keys: vec![same(2)?, same(1)?]
+            }
+            .to_published_json(false)?
+        );
+        let pretty = reversed.to_published_json(true)?;
+        assert!(pretty.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<Value>(&pretty)?,
+            serde_json::from_str::<Value>(&json)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_key_to_jwk_with() -> Result<()> {
+        let k = WithKid::new(
+            "old".into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?,
+        );
+        let jwk = k.public_key_to_jwk_with(Some("new".into()), Some("enc"), false)?;
+        assert_eq!(jwk.kid.as_deref(), Some("new"));
+        assert_eq!(jwk.use_.as_deref(), Some("enc"));
+        assert!(jwk.alg.is_none());
+        assert!(jwk.d.is_none());
+        assert_eq!(
+            jwk.get_thumbprint_sha256()?,
+            k.public_key_to_jwk()?.get_thumbprint_sha256()?
+        );
+
+        let jwk = k.public_key_to_jwk_with(None, None, true)?;
+        assert!(jwk.kid.is_none());
+        assert!(jwk.use_.is_none());
+        assert_eq!(jwk.alg.as_deref(), Some("PS256"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_str() -> Result<()> {
+        let k = Ed25519PrivateKey::generate()?;
+        let json = serde_json::to_string(&k.public_key_to_jwk()?)?;
+        let jwk: Jwk = json.parse()?;
+        assert_eq!(jwk.kty, "OKP");
+        assert!("{".parse::<Jwk>().is_err());
+
+        let set: JwkSet = format!(r#"{{"keys":[{}]}}"#, json).parse()?;
+        assert_eq!(set.keys.len(), 1);
+        let set: JwkSet = format!("[{}]", json).parse()?;
+        assert_eq!(set.keys.len(), 1);
+        assert!(r#"{"kty":"OKP"}"#.parse::<JwkSet>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_alg_must_match() -> Result<()> {
+        let k = WithKid::new(
+            "k".into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?,
+        );
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let mut jwk = k.public_key_to_jwk()?;
+
+        jwk.alg = Some("PS256".into());
+        let set = JwkSet { keys: vec![jwk] };
+        assert!(matches!(
+            set.verifier().verify::<Value>(&token),
+            Err(Error::AlgorithmKeyMismatch)
+        ));
+        let mut verifier = set.verifier();
+        verifier.set_require_kid(false);
+        let token_without_kid = sign(&mut HeaderAndClaims::new_dynamic(), k.as_inner())?;
+        assert!(matches!(
+            verifier.verify::<Value>(&token_without_kid),
+            Err(Error::NoKey)
+        ));
+
+        let mut set = set;
+        set.keys[0].alg = None;
+        set.verifier().verify::<Value>(&token)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_same_kid_different_alg() -> Result<()> {
+        let ec = WithKid::new(
+            "k".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let rsa = WithKid::new(
+            "k".into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?,
+        );
+        let mut set = JwkSet {
+            keys: vec![ec.public_key_to_jwk()?, rsa.public_key_to_jwk()?],
+        };
+        let ec_token = sign(&mut HeaderAndClaims::new_dynamic(), &ec)?;
+        let rsa_token = sign(&mut HeaderAndClaims::new_dynamic(), &rsa)?;
+        let verifier = set.verifier();
+        verifier.verify::<Value>(&ec_token)?;
+        verifier.verify::<Value>(&rsa_token)?;
+        assert!(matches!(
+            verifier.find_with_alg("k", "EdDSA"),
+            Err(Error::NoKey)
+        ));
+
+        let ec1 = WithKid::new(
+            "k".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        set.keys.push(ec1.public_key_to_jwk()?);
+        let verifier = set.verifier();
+        assert!(matches!(
+            verifier.verify::<Value>(&ec_token),
+            Err(Error::AmbiguousKey)
+        ));
+        verifier.verify::<Value>(&rsa_token)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_set_max_keys() -> Result<()> {
+        let keys = |n: usize| {
+            serde_json::to_vec(&serde_json::json!({
+                "keys": vec![serde_json::json!({"kty": "OKP"}); n],
+            }))
+        };
+        assert_eq!(JwkSet::from_json(&keys(100)?)?.keys.len(), 100);
+        assert!(matches!(
+            JwkSet::from_json(&keys(101)?),
+            Err(Error::TooManyKeys)
+        ));
+        assert!(matches!(
+            JwkSet::from_json_lenient(&keys(101)?),
+            Err(Error::TooManyKeys)
+        ));
+        assert!(matches!(
+            JwkSet::from_json_with_max_keys(&keys(3)?, 2),
+            Err(Error::TooManyKeys)
+        ));
+        assert_eq!(
+            JwkSet::from_json_with_max_keys(&keys(101)?, 200)?
+                .keys
+                .len(),
+            101
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_thumbprint() -> Result<()> {
         RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?
@@ -580,6 +1864,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwk_set_from_json() -> Result<()> {
+        let jwk =
+            r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+
+        let standard = format!(r#"{{"keys":[{}]}}"#, jwk);
+        assert_eq!(JwkSet::from_json(standard.as_bytes())?.keys.len(), 1);
+
+        let bare = format!("[{},{}]", jwk, jwk);
+        assert_eq!(JwkSet::from_json(bare.as_bytes())?.keys.len(), 2);
+
+        let wrapped = format!(r#"{{"jwks":{{"keys":[{}]}}}}"#, jwk);
+        assert_eq!(
+            JwkSet::from_json_at(wrapped.as_bytes(), &["jwks"])?
+                .keys
+                .len(),
+            1
+        );
+        assert!(JwkSet::from_json(wrapped.as_bytes()).is_err());
+        assert!(JwkSet::from_json_at(wrapped.as_bytes(), &["other"]).is_err());
+
+        let mut set = JwkSet::from_json(bare.as_bytes())?;
+        assert!(set.validate().is_ok());
+        set.keys.insert(
+            1,
+            Jwk {
+                kty: "RSA".into(),
+                ..Default::default()
+            },
+        );
+        let errors = set.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 1);
+
+        let err = JwkSet::from_json(b"{}").unwrap_err();
+        assert!(err.to_string().contains("expected a JWK Set"));
+
+        Ok(())
+    }
+
     #[derive(Serialize, Deserialize)]
     struct MyClaim {
         foo: String,
@@ -651,4 +1975,457 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jwks_verify_returns_kid() -> Result<()> {
+        let a = WithKid::new("a".into(), Ed25519PrivateKey::generate()?);
+        let b = WithKid::new("b".into(), Ed25519PrivateKey::generate()?);
+        let mut verifier = JwkSet::from_signing_keys(&[&a, &b])?.verifier();
+        let options = VerifyOptions::default();
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &b)?;
+        let (_, kid) = verifier.verify_with_options_and_kid::<Value>(&token, &options)?;
+        assert_eq!(kid, "b");
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), b.as_inner())?;
+        assert!(matches!(
+            verifier.verify_with_options_and_kid::<Value>(&token, &options),
+            Err(Error::NoKey)
+        ));
+        verifier.set_require_kid(false);
+        let (_, kid) = verifier.verify_with_options_and_kid::<Value>(&token, &options)?;
+        assert_eq!(kid, "b");
+        let mut options = options;
+        options.set_require_kid(true);
+        assert!(matches!(
+            verifier.verify_with_options_and_kid::<Value>(&token, &options),
+            Err(Error::NoKid)
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_timeout() -> Result<()> {
+        // Accepts connections (via the backlog) but never responds.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        #[derive(Default)]
+        struct Failures(std::sync::atomic::AtomicUsize);
+        impl JwksObserver for Failures {
+            fn on_fetch_failure(&self, _elapsed: std::time::Duration, error: &Error) {
+                assert!(matches!(error, Error::Timeout));
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+        let failures = std::sync::Arc::new(Failures::default());
+
+        let v = RemoteJwksVerifier::builder(url)
+            .request_timeout(std::time::Duration::from_millis(100))
+            .observer(failures.clone())
+            .build();
+        let res = v.verify::<()>("a.b.c").await;
+        assert!(matches!(res, Err(Error::Timeout)));
+        assert_eq!(failures.0.load(std::sync::atomic::Ordering::Relaxed), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_single_initial_fetch() -> Result<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        #[derive(Default)]
+        struct Fetches(std::sync::atomic::AtomicUsize);
+        impl JwksObserver for Fetches {
+            fn on_fetch_start(&self) {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+        let fetches = std::sync::Arc::new(Fetches::default());
+
+        let v = RemoteJwksVerifier::builder(url)
+            .request_timeout(std::time::Duration::from_millis(100))
+            .observer(fetches.clone())
+            .build();
+        let results = tokio::join!(
+            v.verify::<()>("a.b.c"),
+            v.verify::<()>("a.b.c"),
+            v.verify::<()>("a.b.c"),
+        );
+        assert_eq!(fetches.0.load(std::sync::atomic::Ordering::Relaxed), 1);
+        let results = [results.0, results.1, results.2];
+        assert_eq!(
+            results
+                .iter()
+                .filter(|r| matches!(r, Err(Error::Timeout)))
+                .count(),
+            1
+        );
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(Error::Timeout | Error::JwksUnavailable))));
+
+        // Later requests try again.
+        assert!(matches!(v.initialize().await, Err(Error::Timeout)));
+        assert_eq!(fetches.0.load(std::sync::atomic::Ordering::Relaxed), 2);
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_retry_interval() -> Result<()> {
+        use axum::{http::StatusCode, response::IntoResponse, routing::get};
+        use std::sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let k = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let jwks = serde_json::to_string(&JwkSet {
+            keys: vec![k.public_key_to_jwk()?],
+        })?;
+        let down = Arc::new(AtomicBool::new(false));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new().route(
+            "/jwks",
+            get({
+                let down = down.clone();
+                let fetches = fetches.clone();
+                move || async move {
+                    fetches.fetch_add(1, Ordering::Relaxed);
+                    if down.load(Ordering::Relaxed) {
+                        StatusCode::SERVICE_UNAVAILABLE.into_response()
+                    } else {
+                        jwks.into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let v = RemoteJwksVerifier::builder(url)
+            .cache_duration(std::time::Duration::ZERO)
+            .retry_interval(std::time::Duration::from_millis(200))
+            .build();
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        v.verify::<Value>(&token).await?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        // The stale key set is used, and only one failed refresh is tried.
+        down.store(true, Ordering::Relaxed);
+        for _ in 0..3 {
+            v.verify::<Value>(&token).await?;
+        }
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        v.verify::<Value>(&token).await?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 3);
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_request_decorator() -> Result<()> {
+        use axum::{http::HeaderMap, http::StatusCode, response::IntoResponse, routing::get};
+
+        let k = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let jwks = serde_json::to_string(&JwkSet {
+            keys: vec![k.public_key_to_jwk()?],
+        })?;
+        let app = axum::Router::new().route(
+            "/jwks",
+            get(move |headers: HeaderMap| async move {
+                match headers.get("authorization") {
+                    Some(v) if v == "Bearer secret" => jwks.into_response(),
+                    _ => StatusCode::UNAUTHORIZED.into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+
+        let v = RemoteJwksVerifier::builder(url.clone()).build();
+        assert!(v.verify::<Value>(&token).await.is_err());
+
+        let v = RemoteJwksVerifier::builder(url)
+            .request_decorator(std::sync::Arc::new(|r| r.bearer_auth("secret")))
+            .build();
+        v.verify::<serde_json::Map<String, Value>>(&token).await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_refresh_on_unknown_kid() -> Result<()> {
+        use axum::routing::get;
+        use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc, Mutex};
+
+        let k0 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let k1 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let k2 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let served = Arc::new(Mutex::new(serde_json::to_string(&JwkSet {
+            keys: vec![k0.public_key_to_jwk()?],
+        })?));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new().route(
+            "/jwks",
+            get({
+                let served = served.clone();
+                let fetches = fetches.clone();
+                move || async move {
+                    fetches.fetch_add(1, Ordering::Relaxed);
+                    served.lock().unwrap().clone()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let options = VerifyOptions::default();
+        let v = RemoteJwksVerifier::builder(url)
+            .min_refresh_interval(std::time::Duration::from_millis(200))
+            .build();
+        let token0 = sign(&mut HeaderAndClaims::new_dynamic(), &k0)?;
+        v.verify_with_options::<Value>(&token0, &options).await?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        // A new key is published, tokens signed with it are accepted after a
+        // refresh.
+        *served.lock().unwrap() = serde_json::to_string(&JwkSet {
+            keys: vec![k0.public_key_to_jwk()?, k1.public_key_to_jwk()?],
+        })?;
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let token1 = sign(&mut HeaderAndClaims::new_dynamic(), &k1)?;
+        assert!(matches!(
+            v.verify::<Value>(&token1).await,
+            Err(Error::NoKey)
+        ));
+        v.verify_with_options::<Value>(&token1, &options).await?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+
+        // Unknown kids don't trigger another refresh right away.
+        let token2 = sign(&mut HeaderAndClaims::new_dynamic(), &k2)?;
+        assert!(matches!(
+            v.verify_with_options::<Value>(&token2, &options).await,
+            Err(Error::NoKey)
+        ));
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_jku() -> Result<()> {
+        use axum::routing::get;
+
+        let k0 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let k1 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let jwks0 = serde_json::to_string(&JwkSet {
+            keys: vec![k0.public_key_to_jwk()?],
+        })?;
+        let jwks1 = serde_json::to_string(&JwkSet {
+            keys: vec![k1.public_key_to_jwk()?],
+        })?;
+        let app = axum::Router::new()
+            .route("/jwks", get(move || async move { jwks0 }))
+            .route("/other", get(move || async move { jwks1 }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let other = format!("{}/other", base);
+        let v = RemoteJwksVerifier::builder(format!("{}/jwks", base)).build();
+        let token0 = sign(
+            HeaderAndClaims::new_dynamic().insert_header("jku", other.clone()),
+            &k0,
+        )?;
+        let token1 = sign(
+            HeaderAndClaims::new_dynamic().insert_header("jku", other.clone()),
+            &k1,
+        )?;
+
+        // Ignored by default.
+        let mut options = VerifyOptions::default();
+        v.verify_with_options::<Value>(&token0, &options).await?;
+        assert!(matches!(
+            v.verify_with_options::<Value>(&token1, &options).await,
+            Err(Error::NoKey)
+        ));
+
+        options.set_allowed_jku(&[&other]);
+        v.verify_with_options::<Value>(&token1, &options).await?;
+        assert!(matches!(
+            v.verify_with_options::<Value>(&token0, &options).await,
+            Err(Error::NoKey)
+        ));
+        // No `jku`, the verifier's own key set is used.
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k0)?;
+        v.verify_with_options::<Value>(&token, &options).await?;
+
+        let untrusted = sign(
+            HeaderAndClaims::new_dynamic().insert_header("jku", format!("{}/", other)),
+            &k1,
+        )?;
+        assert!(matches!(
+            v.verify_with_options::<Value>(&untrusted, &options).await,
+            Err(Error::UntrustedJku)
+        ));
+        assert!(matches!(
+            crate::verify_with_options::<Value>(&untrusted, &k1, &options),
+            Err(Error::UntrustedJku)
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_multi_issuer_verifier() -> Result<()> {
+        use axum::routing::get;
+
+        let a = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let b = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let jwks_a = serde_json::to_string(&JwkSet {
+            keys: vec![a.public_key_to_jwk()?],
+        })?;
+        let jwks_b = serde_json::to_string(&JwkSet {
+            keys: vec![b.public_key_to_jwk()?],
+        })?;
+        let app = axum::Router::new()
+            .route("/a", get(move || async move { jwks_a }))
+            .route("/b", get(move || async move { jwks_b }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let v = MultiIssuerVerifier::new();
+        v.add_issuer(
+            "a",
+            RemoteJwksVerifier::builder(format!("{}/a", base)).build(),
+            VerifyOptions::default(),
+        );
+        let mut options = VerifyOptions::default();
+        options.set_audience("gateway");
+        v.add_issuer(
+            "b",
+            RemoteJwksVerifier::builder(format!("{}/b", base)).build(),
+            options,
+        );
+
+        let from_a = sign(HeaderAndClaims::new_dynamic().set_iss("a"), &a)?;
+        v.verify::<Value>(&from_a).await?;
+        let from_b = sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("b")
+                .add_aud("gateway"),
+            &b,
+        )?;
+        v.verify::<Value>(&from_b).await?;
+        // Each issuer's options apply.
+        let wrong_aud = sign(HeaderAndClaims::new_dynamic().set_iss("b"), &b)?;
+        assert!(matches!(
+            v.verify::<Value>(&wrong_aud).await,
+            Err(Error::MissingClaim("aud"))
+        ));
+        // Signed by a, claiming to be b.
+        let forged = sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("b")
+                .add_aud("gateway"),
+            &a,
+        )?;
+        assert!(matches!(
+            v.verify::<Value>(&forged).await,
+            Err(Error::NoKey)
+        ));
+
+        let unknown = sign(HeaderAndClaims::new_dynamic().set_iss("c"), &a)?;
+        assert!(matches!(
+            v.verify::<Value>(&unknown).await,
+            Err(Error::InvalidIssuer)
+        ));
+        let too_long = sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("a")
+                .insert("padding", "x".repeat(VerifyOptions::DEFAULT_MAX_TOKEN_LEN)),
+            &a,
+        )?;
+        assert!(matches!(
+            v.verify::<Value>(&too_long).await,
+            Err(Error::MalformedToken)
+        ));
+
+        assert!(v.remove_issuer("a"));
+        assert!(!v.remove_issuer("a"));
+        assert!(matches!(
+            v.verify::<Value>(&from_a).await,
+            Err(Error::InvalidIssuer)
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test]
+    async fn test_remote_jwks_concurrent_unknown_kid_refreshes() -> Result<()> {
+        use axum::routing::get;
+        use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc, Mutex};
+
+        let k0 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let k1 = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let served = Arc::new(Mutex::new(serde_json::to_string(&JwkSet {
+            keys: vec![k0.public_key_to_jwk()?],
+        })?));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new().route(
+            "/jwks",
+            get({
+                let served = served.clone();
+                let fetches = fetches.clone();
+                move || async move {
+                    fetches.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    served.lock().unwrap().clone()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/jwks", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Not throttled by time, so only deduplication keeps this to one
+        // refresh.
+        let v = Arc::new(
+            RemoteJwksVerifier::builder(url)
+                .min_refresh_interval(std::time::Duration::ZERO)
+                .build(),
+        );
+        v.initialize().await?;
+        *served.lock().unwrap() = serde_json::to_string(&JwkSet {
+            keys: vec![k0.public_key_to_jwk()?, k1.public_key_to_jwk()?],
+        })?;
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k1)?;
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let v = v.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    v.verify_with_options::<Value>(&token, &VerifyOptions::default())
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap()?;
+        }
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+        Ok(())
+    }
 }
@@ -8,19 +8,35 @@ use base64::{
 use openssl::error::ErrorStack;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{Map, Value};
-use serde_with::{serde_as, skip_serializing_none};
+use serde_with::skip_serializing_none;
 use smallvec::SmallVec;
 use std::{
     borrow::Cow,
     fmt,
-    io::Write,
+    io::{Read, Write},
+    ops::Deref,
     string::FromUtf8Error,
+    sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+pub use algorithm::*;
 use jwk::Jwk;
+pub use numeric_date::*;
+pub use rfc9068::*;
+pub use signer::*;
 pub use some::*;
 
+mod algorithm;
+
+mod json;
+
+mod numeric_date;
+
+mod rfc9068;
+
+mod signer;
+
 mod some;
 
 pub mod hmac;
@@ -34,21 +50,68 @@ pub mod rsa;
 pub mod jwk;
 
 /// JWT header.
+///
+/// Serializes as `typ`, `alg`, `kid`, `cty`, then `extra` sorted by name,
+/// regardless of serde_json features, so that signing input is reproducible.
+///
+/// Header parameters that are not modeled as fields are kept in `extra`, so
+/// they survive a deserialize and serialize round trip.
 #[non_exhaustive]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
 pub struct Header {
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub typ: Option<String>,
 
     pub alg: Cow<'static, str>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub kid: Option<String>,
 
+    pub cty: Option<String>,
+
     #[serde(flatten)]
     pub extra: Map<String, Value>,
 }
 
+impl Serialize for Header {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let registered = [
+            ("typ", self.typ.as_deref()),
+            ("alg", Some(&*self.alg)),
+            ("kid", self.kid.as_deref()),
+            ("cty", self.cty.as_deref()),
+        ];
+        let mut extra: Vec<_> = self
+            .extra
+            .iter()
+            .filter(|(k, _)| !registered.iter().any(|(name, _)| name == k))
+            .collect();
+        extra.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        let mut map = serializer.serialize_map(None)?;
+        for (name, value) in registered.iter() {
+            if let Some(value) = value {
+                map.serialize_entry(name, value)?;
+            }
+        }
+        for (k, v) in extra {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+impl Header {
+    /// Parse `alg` as an [`Algorithm`].
+    #[inline]
+    pub fn algorithm(&self) -> Result<Algorithm> {
+        self.alg.parse()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum OneOrMany<T> {
@@ -63,6 +126,15 @@ impl<T> OneOrMany<T> {
     fn is_empty(&self) -> bool {
         matches!(self, OneOrMany::Vec(v) if v.is_empty())
     }
+
+    /// View the value(s) as a slice.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            OneOrMany::One(v) => std::slice::from_ref(v),
+            OneOrMany::Vec(v) => v,
+        }
+    }
 }
 
 impl<T> Default for OneOrMany<T> {
@@ -73,17 +145,13 @@ impl<T> Default for OneOrMany<T> {
 }
 
 /// JWT Claims.
-#[serde_as]
 #[skip_serializing_none]
 #[non_exhaustive]
 #[derive(Debug, Serialize, Default, Deserialize)]
 pub struct Claims<ExtraClaims> {
-    #[serde_as(as = "Option<serde_with::DurationSeconds<f64>>")]
-    pub exp: Option<Duration>,
-    #[serde_as(as = "Option<serde_with::DurationSeconds<f64>>")]
-    pub nbf: Option<Duration>,
-    #[serde_as(as = "Option<serde_with::DurationSeconds<f64>>")]
-    pub iat: Option<Duration>,
+    pub exp: Option<NumericDate>,
+    pub nbf: Option<NumericDate>,
+    pub iat: Option<NumericDate>,
 
     pub iss: Option<String>,
     pub sub: Option<String>,
@@ -95,6 +163,53 @@ pub struct Claims<ExtraClaims> {
     pub extra: ExtraClaims,
 }
 
+impl<ExtraClaims> Claims<ExtraClaims> {
+    /// Subject (`sub`).
+    #[inline]
+    pub fn sub(&self) -> Option<&str> {
+        self.sub.as_deref()
+    }
+
+    /// Issuer (`iss`).
+    #[inline]
+    pub fn iss(&self) -> Option<&str> {
+        self.iss.as_deref()
+    }
+
+    /// Expiration time (`exp`) in whole seconds since the unix epoch.
+    #[inline]
+    pub fn exp(&self) -> Option<u64> {
+        self.exp.map(NumericDate::as_secs)
+    }
+
+    /// Audiences (`aud`), whether encoded as a single string or an array.
+    #[inline]
+    pub fn aud(&self) -> &[String] {
+        self.aud.as_slice()
+    }
+
+    /// Check whether `exp` is present and in the past, according to the
+    /// system clock. Use [`is_expired_at`](Self::is_expired_at) with the
+    /// time passed to [`VerifyOptions::set_now`], if any.
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(SystemTime::now())
+    }
+
+    /// Check whether `exp` is present and before `now`.
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.exp.is_some_and(|exp| is_after(now, exp))
+    }
+
+    /// How long until `exp`, as seen at `now`, saturating to zero. `None` if
+    /// there is no `exp`.
+    ///
+    /// Pass the same time as [`VerifyOptions::set_now`], or
+    /// `SystemTime::now()`.
+    pub fn time_until_expiry(&self, now: SystemTime) -> Option<Duration> {
+        Some(self.exp?.to_duration().saturating_sub(since_epoch(now)))
+    }
+}
+
 /// JWT header and claims.
 ///
 /// # `ExtraClaims`
@@ -153,6 +268,73 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
         &self.claims
     }
 
+    /// See [`Claims::sub`].
+    #[inline]
+    pub fn sub(&self) -> Option<&str> {
+        self.claims.sub()
+    }
+
+    /// See [`Claims::iss`].
+    #[inline]
+    pub fn iss(&self) -> Option<&str> {
+        self.claims.iss()
+    }
+
+    /// See [`Claims::exp`].
+    #[inline]
+    pub fn exp(&self) -> Option<u64> {
+        self.claims.exp()
+    }
+
+    /// See [`Claims::aud`].
+    #[inline]
+    pub fn aud(&self) -> &[String] {
+        self.claims.aud()
+    }
+
+    /// See [`Claims::is_expired`].
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.claims.is_expired()
+    }
+
+    /// See [`Claims::is_expired_at`].
+    #[inline]
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.claims.is_expired_at(now)
+    }
+
+    /// See [`Claims::time_until_expiry`].
+    #[inline]
+    pub fn time_until_expiry(&self, now: SystemTime) -> Option<Duration> {
+        self.claims.time_until_expiry(now)
+    }
+
+    /// Split into the header, the registered claims and the extra claims.
+    pub fn into_parts(self) -> (Header, Claims<()>, ExtraClaims) {
+        let Claims {
+            exp,
+            nbf,
+            iat,
+            iss,
+            sub,
+            aud,
+            jti,
+            extra,
+        } = self.claims;
+        let claims = Claims {
+            exp,
+            nbf,
+            iat,
+            iss,
+            sub,
+            aud,
+            jti,
+            extra: (),
+        };
+        (self.header, claims, extra)
+    }
+
     #[inline]
     pub fn header_mut(&mut self) -> &mut Header {
         &mut self.header
@@ -169,6 +351,25 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
         self
     }
 
+    #[inline]
+    pub fn set_typ(&mut self, typ: impl Into<String>) -> &mut Self {
+        self.header.typ = Some(typ.into());
+        self
+    }
+
+    #[inline]
+    pub fn set_cty(&mut self, cty: impl Into<String>) -> &mut Self {
+        self.header.cty = Some(cty.into());
+        self
+    }
+
+    /// Set an extra (non-registered) header parameter.
+    #[inline]
+    pub fn insert_header(&mut self, k: impl Into<String>, v: impl Into<Value>) -> &mut Self {
+        self.header.extra.insert(k.into(), v.into());
+        self
+    }
+
     define_setter!(set_iss, iss);
     define_setter!(set_sub, sub);
     define_setter!(set_jti, jti);
@@ -192,11 +393,16 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
 
     /// Set token issued-at time (`iat`) to the current system time, i.e.
     /// `SystemTime::now()`.
+    ///
+    /// # Panics
+    ///
+    /// If the system clock is before the unix epoch.
     pub fn set_iat_now(&mut self) -> &mut Self {
         self.claims.iat = Some(
             SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap(),
+                .unwrap()
+                .into(),
         );
         self
     }
@@ -205,26 +411,37 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
     pub fn iat_is_later_than(&self, t: SystemTime) -> bool {
         self.claims
             .iat
-            .map_or(false, |iat| iat > t.duration_since(UNIX_EPOCH).unwrap())
+            .is_some_and(|iat| match t.duration_since(UNIX_EPOCH) {
+                Ok(t) => iat.to_duration() > t,
+                Err(_) => true,
+            })
     }
 
     /// Set token expiration time (`exp`) to some time after the current time,
     /// i.e., `SystemTime::now() + dur`.
+    ///
+    /// # Panics
+    ///
+    /// If the system clock is before the unix epoch.
     pub fn set_exp_from_now(&mut self, dur: Duration) -> &mut Self {
         let t = (SystemTime::now() + dur)
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
-        self.claims.exp = Some(t);
+        self.claims.exp = Some(t.into());
         self
     }
 
     /// Set token not-before time (`nbf`) to some time after the current time,
     /// i.e., `SystemTime::now() + dur`.
+    ///
+    /// # Panics
+    ///
+    /// If the system clock is before the unix epoch.
     pub fn set_nbf_from_now(&mut self, dur: Duration) -> &mut Self {
         let t = (SystemTime::now() + dur)
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
-        self.claims.nbf = Some(t);
+        self.claims.nbf = Some(t.into());
         self
     }
 }
@@ -252,21 +469,45 @@ pub fn sign<ExtraClaims: Serialize>(
     claims: &mut HeaderAndClaims<ExtraClaims>,
     k: &dyn SigningKey,
 ) -> Result<String> {
-    claims.header.alg = k.alg().into();
+    let mut w = encode_header(&mut claims.header, k)?;
+    serde_json::to_writer(&mut w, &claims.claims)?;
+    append_signature(w.into_inner(), k)
+}
+
+/// Sign `payload` as is, e.g. to re-sign a token without changing the bytes
+/// of its payload. `alg` and `kid` of `header` are set like [`sign`] does.
+///
+/// `payload` is not checked to be JSON.
+pub fn sign_raw_payload(header: &mut Header, payload: &[u8], k: &dyn SigningKey) -> Result<String> {
+    let mut w = encode_header(header, k)?;
+    w.write_all(payload)?;
+    append_signature(w.into_inner(), k)
+}
+
+// Set `alg` and `kid`, and return a writer for the payload segment after the
+// encoded header.
+fn encode_header(
+    header: &mut Header,
+    k: &dyn SigningKey,
+) -> Result<base64::write::EncoderStringWriter<'static, GeneralPurpose, String>> {
+    header.alg = k.alg().into();
     if let Some(kid) = k.kid() {
-        claims.set_kid(kid);
+        header.kid = Some(kid.into());
     }
 
     let mut w = base64::write::EncoderStringWriter::new(&URL_SAFE_TRAILING_BITS);
-    serde_json::to_writer(&mut w, &claims.header)?;
+    serde_json::to_writer(&mut w, &*header)?;
 
     let mut buf = w.into_inner();
     buf.push('.');
-    let mut w = base64::write::EncoderStringWriter::from_consumer(buf, &URL_SAFE_TRAILING_BITS);
-
-    serde_json::to_writer(&mut w, &claims.claims)?;
-    let mut buf = w.into_inner();
+    Ok(base64::write::EncoderStringWriter::from_consumer(
+        buf,
+        &URL_SAFE_TRAILING_BITS,
+    ))
+}
 
+// Sign `buf`, i.e. `header.payload`, and append the signature.
+fn append_signature(mut buf: String, k: &dyn SigningKey) -> Result<String> {
     let sig = k.sign(buf.as_bytes())?;
 
     buf.push('.');
@@ -276,262 +517,2818 @@ pub fn sign<ExtraClaims: Serialize>(
     Ok(w.into_inner())
 }
 
-/// Decode and verify token.
+/// Constraints on keys, checked when loading them with the `*_with_policy`
+/// functions, e.g. [`SomePublicKey::from_pem_with_policy`].
 ///
-/// The `alg`, `exp` and `nbf` fields are automatically checked.
-pub fn verify<ExtraClaims: DeserializeOwned>(
-    token: &str,
-    k: &dyn VerificationKey,
-) -> Result<HeaderAndClaims<ExtraClaims>> {
-    let claims = verify_only(token, k)?;
-
-    // Check exp and nbf.
-    let now = SystemTime::now();
-    if let Some(exp) = claims.claims.exp {
-        let exp = SystemTime::UNIX_EPOCH + exp;
-        if now > exp {
-            return Err(Error::Expired);
-        }
-    }
-    if let Some(nbf) = claims.claims.nbf {
-        let nbf = SystemTime::UNIX_EPOCH + nbf;
-        if now < nbf {
-            return Err(Error::Before);
-        }
-    }
-
-    Ok(claims)
+/// Capping the RSA key size protects against very large keys from untrusted
+/// sources, which would make verification expensive.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// Minimum RSA key size in bits. Defaults to 2048. Values below 2048 are
+    /// not allowed.
+    pub min_rsa_bits: u32,
+    /// Maximum RSA key size in bits. Defaults to no limit.
+    pub max_rsa_bits: Option<u32>,
+    /// Reject RSA private keys smaller than 3072 bits with
+    /// [`Error::KeyTooWeakForSigning`]. Public keys are not affected, so
+    /// tokens signed with existing 2048-bit keys can still be verified.
+    /// Defaults to `false`.
+    pub require_strong_signing_keys: bool,
 }
 
-/// Decode and verify token, but do not check `exp` and `nbf`.
-///
-/// The `alg` field is still checked.
-pub fn verify_only<ExtraClaims: DeserializeOwned>(
-    token: &str,
-    k: &dyn VerificationKey,
-) -> Result<HeaderAndClaims<ExtraClaims>> {
-    let mut parts = token.split('.');
-
-    let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let mut payload = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let header_and_payload_len = header.len() + payload.len() + 1;
-    let sig = parts.next().ok_or(Error::InvalidToken)?;
-    if parts.next().is_some() {
-        return Err(Error::InvalidToken);
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        Self {
+            min_rsa_bits: 2048,
+            max_rsa_bits: None,
+            require_strong_signing_keys: false,
+        }
     }
-
-    let header_r = base64::read::DecoderReader::new(&mut header, &URL_SAFE_TRAILING_BITS);
-    let header: Header = serde_json::from_reader(header_r)?;
-
-    let sig = URL_SAFE_TRAILING_BITS.decode(sig)?;
-
-    // Verify the signature.
-    k.verify(
-        token[..header_and_payload_len].as_bytes(),
-        &sig,
-        &header.alg,
-    )?;
-
-    let payload_r = base64::read::DecoderReader::new(&mut payload, &URL_SAFE_TRAILING_BITS);
-    let claims: Claims<ExtraClaims> = serde_json::from_reader(payload_r)?;
-
-    Ok(HeaderAndClaims { header, claims })
 }
 
-/// Decode token.
-///
-/// No verification or validation is performed.
-pub fn decode_without_verify<ExtraClaims: DeserializeOwned>(
-    token: &str,
-) -> Result<HeaderAndClaims<ExtraClaims>> {
-    let mut parts = token.split('.');
-
-    let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let mut payload = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let _sig = parts.next().ok_or(Error::InvalidToken)?;
-    if parts.next().is_some() {
-        return Err(Error::InvalidToken);
+impl KeyPolicy {
+    pub(crate) fn check_rsa_bits(&self, bits: u32) -> Result<()> {
+        if bits < 2048
+            || bits < self.min_rsa_bits
+            || self.max_rsa_bits.is_some_and(|max| bits > max)
+        {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+        Ok(())
     }
 
-    let header_r = base64::read::DecoderReader::new(&mut header, &URL_SAFE_TRAILING_BITS);
-    let header: Header = serde_json::from_reader(header_r)?;
-
-    let payload_r = base64::read::DecoderReader::new(&mut payload, &URL_SAFE_TRAILING_BITS);
-    let claims: Claims<ExtraClaims> = serde_json::from_reader(payload_r)?;
-
-    Ok(HeaderAndClaims { header, claims })
-}
-
-pub trait SigningKey {
-    // A signing key has a rigid algorithm.
-    fn alg(&self) -> &'static str;
-
-    /// Optional key id. If it is present, then it is automatically set in
-    /// header claims.
-    fn kid(&self) -> Option<&str> {
-        None
+    pub(crate) fn check_rsa_signing_bits(&self, bits: u32) -> Result<()> {
+        self.check_rsa_bits(bits)?;
+        if self.require_strong_signing_keys && bits < 3072 {
+            return Err(Error::KeyTooWeakForSigning);
+        }
+        Ok(())
     }
-
-    // Es256 and eddsa signatures are 64-byte long.
-    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>>;
-}
-
-pub trait VerificationKey {
-    // `alg` is passed in because HMAC and RSA verification keys can verify
-    // signatures generated with multiple algorithms.
-    fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()>;
-}
-
-pub trait PublicKeyToJwk {
-    fn public_key_to_jwk(&self) -> Result<Jwk>;
 }
 
-pub trait PrivateKeyToJwk {
-    fn private_key_to_jwk(&self) -> Result<Jwk>;
-}
+/// See [`VerifyOptions::set_payload_canonicalization`].
+pub type CanonicalizePayload = fn(&[u8]) -> Result<Vec<u8>>;
 
+/// Claim validation options for [`verify_with_options`].
+///
+/// The default options only check `exp` and `nbf`, i.e. the same as
+/// [`verify`]. Prefer [`VerifyOptions::builder`], which requires an algorithm
+/// allowlist.
 #[non_exhaustive]
-#[derive(Debug)]
-pub enum Error {
-    InvalidToken,
-    VerificationError,
-    AlgMismatch,
-    NoKid,
-    NoKey,
-    Expired,
-    /// The token is not valid yet , i.e. `nbf` check failed.
-    Before,
-    UnsupportedOrInvalidKey,
-    Utf8(FromUtf8Error),
-    IoError(std::io::Error),
-    OpenSsl(ErrorStack),
-    SerdeJson(serde_json::Error),
-    Decode(base64::DecodeError),
-    #[cfg(feature = "remote-jwks")]
-    Reqwest(reqwest::Error),
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    allowed_algorithms: Option<Vec<Algorithm>>,
+    leeway: Duration,
+    issuer: Option<String>,
+    audience: Option<String>,
+    required_claims: Vec<&'static str>,
+    max_token_age: Option<Duration>,
+    max_lifetime: Option<Duration>,
+    skip_temporal_validation: bool,
+    now: Option<SystemTime>,
+    header_repair: Option<fn(&mut Header, &Value)>,
+    tolerate_sig_padding: bool,
+    case_insensitive_alg: bool,
+    max_token_len: Option<usize>,
+    allowed_typs: Option<Vec<String>>,
+    allow_missing_typ: bool,
+    allow_duplicate_keys: bool,
+    require_kid: bool,
+    allowed_jku: Vec<String>,
+    understood_crit: Vec<String>,
+    canonicalize_payload: Option<CanonicalizePayload>,
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::IoError(e) => e.fmt(f),
-            Error::OpenSsl(e) => e.fmt(f),
-            Error::SerdeJson(e) => e.fmt(f),
-            Error::Decode(e) => e.fmt(f),
-            #[cfg(feature = "remote-jwks")]
-            Error::Reqwest(e) => e.fmt(f),
-            Error::Utf8(e) => e.fmt(f),
-            Error::VerificationError => "failed to verify signature".fmt(f),
-            Error::AlgMismatch => {
-                "the alg field in JWT header is different from what the verification key uses"
-                    .fmt(f)
-            }
-            Error::InvalidToken => "the token not in a valid format".fmt(f),
-            Error::NoKid => "the kid field is missing from the JWT header".fmt(f),
-            Error::NoKey => "no key in the JWK Set matches the kid".fmt(f),
-            Error::UnsupportedOrInvalidKey => "unsupported or invalid key".fmt(f),
-            Error::Expired => "token expired (exp check failed)".fmt(f),
-            Error::Before => "token is not valid yet (nbf check failed)".fmt(f),
-        }
+impl VerifyOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
     }
-}
 
-impl std::error::Error for Error {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Error::IoError(e) => Some(e),
-            Error::OpenSsl(e) => Some(e),
-            Error::SerdeJson(e) => Some(e),
-            Error::Decode(e) => Some(e),
-            Error::Utf8(e) => Some(e),
-            #[cfg(feature = "remote-jwks")]
-            Error::Reqwest(e) => Some(e),
-            _ => None,
+    #[inline]
+    pub fn builder() -> VerifyOptionsBuilder {
+        VerifyOptionsBuilder {
+            options: Self::default(),
         }
     }
-}
 
-impl From<std::io::Error> for Error {
-    #[inline]
-    fn from(e: std::io::Error) -> Error {
-        Error::IoError(e)
+    /// Reject tokens whose `alg` is not one of `algorithms` with
+    /// [`Error::AlgorithmNotAllowed`], before the signature is checked.
+    pub fn set_allowed_algorithms(&mut self, algorithms: &[Algorithm]) -> &mut Self {
+        self.allowed_algorithms = Some(algorithms.to_vec());
+        self
     }
-}
 
-impl From<ErrorStack> for Error {
+    /// Accept tokens up to `leeway` after `exp` and before `nbf`, to allow for
+    /// clock skew. Defaults to zero.
     #[inline]
-    fn from(e: ErrorStack) -> Error {
-        Error::OpenSsl(e)
+    pub fn set_leeway(&mut self, leeway: Duration) -> &mut Self {
+        self.leeway = leeway;
+        self
     }
-}
 
-impl From<serde_json::Error> for Error {
+    /// Require `iss` to be `issuer`, or fail with [`Error::InvalidIssuer`].
     #[inline]
-    fn from(e: serde_json::Error) -> Error {
-        Error::SerdeJson(e)
+    pub fn set_issuer(&mut self, issuer: impl Into<String>) -> &mut Self {
+        self.issuer = Some(issuer.into());
+        self
     }
-}
 
-impl From<base64::DecodeError> for Error {
+    /// Require `aud` to contain `audience`, or fail with
+    /// [`Error::InvalidAudience`].
     #[inline]
-    fn from(e: base64::DecodeError) -> Self {
-        Error::Decode(e)
+    pub fn set_audience(&mut self, audience: impl Into<String>) -> &mut Self {
+        self.audience = Some(audience.into());
+        self
     }
-}
 
-impl From<FromUtf8Error> for Error {
-    #[inline]
-    fn from(e: FromUtf8Error) -> Self {
-        Error::Utf8(e)
+    /// Require the payload to have these claims, registered or not, or fail
+    /// with [`Error::MissingClaim`].
+    pub fn set_required_claims(&mut self, claims: &[&'static str]) -> &mut Self {
+        self.required_claims = claims.to_vec();
+        self
     }
-}
 
-#[cfg(feature = "remote-jwks")]
-impl From<reqwest::Error> for Error {
+    /// Reject tokens that were issued (`iat`) more than `max_age` ago, e.g.
+    /// to require a freshly issued token for step-up authentication. This is
+    /// independent of `exp`.
+    ///
+    /// When set, `iat` is required.
     #[inline]
-    fn from(e: reqwest::Error) -> Self {
-        Error::Reqwest(e)
+    pub fn set_max_token_age(&mut self, max_age: Duration) -> &mut Self {
+        self.max_token_age = Some(max_age);
+        self
+    }
+
+    /// Reject tokens whose declared lifetime, i.e. `exp - iat`, is longer
+    /// than `max_lifetime` with [`Error::LifetimeTooLong`], whether they have
+    /// expired or not. Unlike [`set_max_token_age`](Self::set_max_token_age),
+    /// this doesn't depend on the current time.
+    ///
+    /// When set, `exp` and `iat` are required.
+    #[inline]
+    pub fn set_max_lifetime(&mut self, max_lifetime: Duration) -> &mut Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// **Dangerous**: do not reject tokens based on time, i.e. skip the
+    /// `exp`, `nbf` and max token age checks. The signature and all other
+    /// checks still apply.
+    ///
+    /// This is only meant for things like telling the user *when* their
+    /// session expired with a trustworthy timestamp. Never use the result to
+    /// grant access; check [`Claims::is_expired`] on the returned claims.
+    #[inline]
+    pub fn set_skip_temporal_validation(&mut self, skip: bool) -> &mut Self {
+        self.skip_temporal_validation = skip;
+        self
+    }
+
+    /// Validate time based claims against `now` instead of
+    /// `SystemTime::now()`.
+    ///
+    /// Verification fails with [`Error::ClockError`] if the current time is
+    /// before the unix epoch, which can happen on devices whose clock has not
+    /// been synced. Sync the clock first, or inject a known good time here.
+    #[inline]
+    pub fn set_now(&mut self, now: SystemTime) -> &mut Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Interop escape hatch for producers that put header parameters (e.g.
+    /// `typ`) in the payload: `repair` is called with the header and the
+    /// payload before the claims are validated.
+    ///
+    /// It only runs after the signature has been verified, so it cannot
+    /// affect which key or algorithm is used.
+    #[inline]
+    pub fn set_header_repair(&mut self, repair: fn(&mut Header, &Value)) -> &mut Self {
+        self.header_repair = Some(repair);
+        self
+    }
+
+    /// Accept `=` padding at the end of the signature segment, as emitted by
+    /// some broken SDKs. The header and payload are still strictly unpadded
+    /// base64url.
+    #[inline]
+    pub fn set_tolerate_sig_padding(&mut self, tolerate: bool) -> &mut Self {
+        self.tolerate_sig_padding = tolerate;
+        self
+    }
+
+    /// Accept a header `alg` that only differs in case from a known
+    /// algorithm, e.g. `rs256`, as emitted by some non-conforming producers.
+    /// The `alg` is normalized, e.g. to `RS256`, before the key is selected
+    /// and checked. Defaults to `false`, i.e. an exact match.
+    #[inline]
+    pub fn set_case_insensitive_alg(&mut self, case_insensitive: bool) -> &mut Self {
+        self.case_insensitive_alg = case_insensitive;
+        self
+    }
+
+    /// The default of [`set_max_token_len`](Self::set_max_token_len), 16 KiB.
+    pub const DEFAULT_MAX_TOKEN_LEN: usize = 16 * 1024;
+
+    /// Reject tokens longer than `len` bytes with [`Error::MalformedToken`],
+    /// before anything is decoded. Defaults to
+    /// [`DEFAULT_MAX_TOKEN_LEN`](Self::DEFAULT_MAX_TOKEN_LEN). Use
+    /// `usize::MAX` for no limit.
+    #[inline]
+    pub fn set_max_token_len(&mut self, len: usize) -> &mut Self {
+        self.max_token_len = Some(len);
+        self
+    }
+
+    /// Require the `typ` header to be one of `typs`, e.g. `["JWT", "at+jwt"]`,
+    /// or fail with [`Error::InvalidTyp`].
+    ///
+    /// Values are matched case-insensitively, and an `application/` prefix is
+    /// ignored, so `at+jwt` also accepts `application/at+JWT`. Tokens without
+    /// `typ` are rejected, unless allowed with
+    /// [`set_allow_missing_typ`](Self::set_allow_missing_typ).
+    pub fn set_require_typ(&mut self, typs: &[&str]) -> &mut Self {
+        self.allowed_typs = Some(typs.iter().map(|typ| typ.to_string()).collect());
+        self
+    }
+
+    /// Accept tokens without `typ` even if
+    /// [`set_require_typ`](Self::set_require_typ) is used.
+    #[inline]
+    pub fn set_allow_missing_typ(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing_typ = allow;
+        self
+    }
+
+    /// Accept JSON objects with duplicate member names, e.g. two `exp`, in the
+    /// header and payload. The last one wins. Defaults to `false`, i.e. such
+    /// tokens are rejected with [`Error::MalformedToken`], as parsers that
+    /// pick different members could be told apart to smuggle claims.
+    ///
+    /// [`decode_without_verify`] and [`inspect`] always accept them.
+    #[inline]
+    pub fn set_allow_duplicate_keys(&mut self, allow: bool) -> &mut Self {
+        self.allow_duplicate_keys = allow;
+        self
+    }
+
+    /// Reject tokens without a `kid` header with [`Error::NoKid`] right after
+    /// the header is parsed. Defaults to `false`.
+    #[inline]
+    pub fn set_require_kid(&mut self, required: bool) -> &mut Self {
+        self.require_kid = required;
+        self
+    }
+
+    /// Trust the `jku` (JWK Set URL) header if it is exactly one of `urls`.
+    ///
+    /// Tokens with any other `jku` fail with [`Error::UntrustedJku`] before
+    /// the signature is checked. `RemoteJwksVerifier`, with the `remote-jwks`
+    /// feature, then verifies tokens with a trusted `jku` with the key set
+    /// fetched from there.
+    ///
+    /// By default `jku` is ignored, which is what you want unless you know
+    /// you need it: fetching key sets from URLs given by the token is prone to
+    /// SSRF.
+    pub fn set_allowed_jku(&mut self, urls: &[&str]) -> &mut Self {
+        self.allowed_jku = urls.iter().map(|url| url.to_string()).collect();
+        self
+    }
+
+    /// Accept tokens that mark these header parameters as critical (`crit`),
+    /// by vouching that the application understands and checks them.
+    ///
+    /// Tokens with a `crit` header are rejected with
+    /// [`Error::UnsupportedCrit`] if it lists any other parameter, or if it
+    /// is malformed, e.g. lists a parameter that is not in the header. This
+    /// crate itself understands no `crit` parameters.
+    pub fn set_understood_crit(&mut self, names: &[&str]) -> &mut Self {
+        self.understood_crit = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Apply `canonicalize` to the payload before checking the signature in
+    /// [`verify_detached_unencoded`], e.g. for a partner that signs the RFC
+    /// 8785 (JCS) form of a JSON payload.
+    #[inline]
+    pub fn set_payload_canonicalization(&mut self, canonicalize: CanonicalizePayload) -> &mut Self {
+        self.canonicalize_payload = Some(canonicalize);
+        self
+    }
+
+    fn check_crit(&self, header: &Header) -> Result<()> {
+        self.check_crit_with(header, &[])
+    }
+
+    // RFC 7515 section 4.1.11. `builtin` are parameters understood by the
+    // calling verification path.
+    fn check_crit_with(&self, header: &Header, builtin: &[&str]) -> Result<()> {
+        const REGISTERED: &[&str] = &[
+            "alg", "jku", "jwk", "kid", "x5u", "x5c", "x5t", "x5t#S256", "typ", "cty", "crit",
+        ];
+        let Some(crit) = header.extra.get("crit") else {
+            return Ok(());
+        };
+        let Value::Array(names) = crit else {
+            return Err(Error::UnsupportedCrit);
+        };
+        if names.is_empty() {
+            return Err(Error::UnsupportedCrit);
+        }
+        for name in names {
+            let ok = match name {
+                Value::String(name) => {
+                    !REGISTERED.contains(&&**name)
+                        && header.extra.contains_key(name)
+                        && (self.understood_crit.contains(name) || builtin.contains(&&**name))
+                }
+                _ => false,
+            };
+            if !ok {
+                return Err(Error::UnsupportedCrit);
+            }
+        }
+        Ok(())
+    }
+
+    // The `jku` of `header`, if it is allowlisted. Fails if it is not.
+    pub(crate) fn check_jku<'h>(&self, header: &'h Header) -> Result<Option<&'h str>> {
+        if self.allowed_jku.is_empty() {
+            return Ok(None);
+        }
+        match header.extra.get("jku") {
+            None => Ok(None),
+            Some(Value::String(jku)) if self.allowed_jku.contains(jku) => Ok(Some(jku)),
+            Some(_) => Err(Error::UntrustedJku),
+        }
+    }
+
+    fn check_typ(&self, header: &Header) -> Result<()> {
+        let Some(allowed) = &self.allowed_typs else {
+            return Ok(());
+        };
+        fn normalize(typ: &str) -> String {
+            let typ = typ.to_ascii_lowercase();
+            match typ.strip_prefix("application/") {
+                Some(t) => t.into(),
+                None => typ,
+            }
+        }
+        let ok = match &header.typ {
+            Some(typ) => {
+                let typ = normalize(typ);
+                allowed.iter().any(|a| normalize(a) == typ)
+            }
+            None => self.allow_missing_typ,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::InvalidTyp)
+        }
+    }
+
+    pub(crate) fn token_len_limit(&self) -> usize {
+        self.max_token_len.unwrap_or(Self::DEFAULT_MAX_TOKEN_LEN)
+    }
+
+    pub(crate) fn check_len(&self, token: &[u8]) -> Result<()> {
+        if token.len() > self.token_len_limit() {
+            return Err(Error::MalformedToken);
+        }
+        Ok(())
+    }
+
+    // Normalize the case of `alg`, if allowed by `set_case_insensitive_alg`.
+    pub(crate) fn normalize_alg(&self, header: &mut Header) {
+        if !self.case_insensitive_alg {
+            return;
+        }
+        if let Some(alg) = Algorithm::ALL
+            .iter()
+            .find(|alg| alg.as_str().eq_ignore_ascii_case(&header.alg))
+        {
+            header.alg = alg.as_str().into();
+        }
+    }
+
+    pub(crate) fn check_kid(&self, header: &Header) -> Result<()> {
+        if self.require_kid && header.kid.is_none() {
+            return Err(Error::NoKid);
+        }
+        Ok(())
+    }
+
+    fn check_alg(&self, header: &Header) -> Result<()> {
+        match &self.allowed_algorithms {
+            Some(allowed) if !allowed.iter().any(|alg| alg.as_str() == header.alg) => {
+                Err(Error::AlgorithmNotAllowed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Decode the payload of a token whose signature has been verified, then
+    // validate the claims.
+    fn decode_and_check<ExtraClaims: DeserializeOwned>(
+        &self,
+        header: Header,
+        payload: &[u8],
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        self.parse_and_check(header, &decode_segment(payload, Segment::Payload)?)
+    }
+
+    // Like `decode_and_check`, but `payload` is already decoded.
+    fn parse_and_check<ExtraClaims: DeserializeOwned>(
+        &self,
+        mut header: Header,
+        payload: &[u8],
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        let claims = if self.header_repair.is_some() || !self.required_claims.is_empty() {
+            let payload: Value = parse_json(payload, self.allow_duplicate_keys)?;
+            if let Some(repair) = self.header_repair {
+                repair(&mut header, &payload);
+            }
+            if let Some(missing) = self
+                .required_claims
+                .iter()
+                .find(|name| matches!(payload.get(**name), None | Some(Value::Null)))
+            {
+                return Err(Error::MissingClaim(missing));
+            }
+            HeaderAndClaims {
+                header,
+                claims: serde_json::from_value(payload)?,
+            }
+        } else {
+            HeaderAndClaims {
+                header,
+                claims: parse_json(payload, self.allow_duplicate_keys)?,
+            }
+        };
+        self.check_typ(&claims.header)?;
+        self.check(&claims.claims)?;
+        Ok(claims)
+    }
+
+    fn check<ExtraClaims>(&self, claims: &Claims<ExtraClaims>) -> Result<()> {
+        if let Some(issuer) = &self.issuer {
+            let iss = claims.iss.as_ref().ok_or(Error::MissingClaim("iss"))?;
+            if iss != issuer {
+                return Err(Error::InvalidIssuer);
+            }
+        }
+        if let Some(audience) = &self.audience {
+            if claims.aud.is_empty() {
+                return Err(Error::MissingClaim("aud"));
+            }
+            if !claims.aud().contains(audience) {
+                return Err(Error::InvalidAudience);
+            }
+        }
+        if let Some(max_lifetime) = self.max_lifetime {
+            let exp = claims.exp.ok_or(Error::MissingClaim("exp"))?;
+            let iat = claims.iat.ok_or(Error::MissingClaim("iat"))?;
+            let lifetime = exp.to_duration().saturating_sub(iat.to_duration());
+            if lifetime > max_lifetime {
+                return Err(Error::LifetimeTooLong);
+            }
+        }
+        if self.skip_temporal_validation {
+            return Ok(());
+        }
+        let now = self.now.unwrap_or_else(SystemTime::now);
+        if now < SystemTime::UNIX_EPOCH {
+            return Err(Error::ClockError);
+        }
+        if let Some(exp) = claims.exp {
+            if since_epoch(now) > exp.to_duration().saturating_add(self.leeway) {
+                return Err(Error::Expired);
+            }
+        }
+        if let Some(nbf) = claims.nbf {
+            if since_epoch(now).saturating_add(self.leeway) < nbf.to_duration() {
+                return Err(Error::Before);
+            }
+        }
+        if let Some(max_age) = self.max_token_age {
+            let iat = claims.iat.ok_or(Error::MissingClaim("iat"))?;
+            let fresh_until = iat.to_duration().checked_add(max_age);
+            if fresh_until.is_some_and(|t| since_epoch(now) > t) {
+                return Err(Error::TokenTooOld);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`VerifyOptions`], see [`VerifyOptions::builder`].
+///
+/// [`build`](Self::build) fails unless an algorithm allowlist is given.
+/// Everything else defaults to the strict setting, e.g. no leeway and no
+/// padding in the signature. `typ` is not restricted.
+#[derive(Debug, Clone)]
+pub struct VerifyOptionsBuilder {
+    options: VerifyOptions,
+}
+
+impl VerifyOptionsBuilder {
+    /// See [`VerifyOptions::set_allowed_algorithms`]. Required.
+    pub fn algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        self.options.set_allowed_algorithms(algorithms);
+        self
+    }
+
+    /// See [`VerifyOptions::set_issuer`].
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.options.set_issuer(issuer);
+        self
+    }
+
+    /// See [`VerifyOptions::set_audience`].
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.options.set_audience(audience);
+        self
+    }
+
+    /// See [`VerifyOptions::set_leeway`].
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.options.set_leeway(leeway);
+        self
+    }
+
+    /// See [`VerifyOptions::set_max_token_len`].
+    pub fn max_token_len(mut self, len: usize) -> Self {
+        self.options.set_max_token_len(len);
+        self
+    }
+
+    /// See [`VerifyOptions::set_max_token_age`].
+    pub fn max_token_age(mut self, max_age: Duration) -> Self {
+        self.options.set_max_token_age(max_age);
+        self
+    }
+
+    /// See [`VerifyOptions::set_max_lifetime`].
+    pub fn max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.options.set_max_lifetime(max_lifetime);
+        self
+    }
+
+    /// See [`VerifyOptions::set_case_insensitive_alg`].
+    pub fn case_insensitive_alg(mut self, case_insensitive: bool) -> Self {
+        self.options.set_case_insensitive_alg(case_insensitive);
+        self
+    }
+
+    /// See [`VerifyOptions::set_allow_duplicate_keys`].
+    pub fn allow_duplicate_keys(mut self, allow: bool) -> Self {
+        self.options.set_allow_duplicate_keys(allow);
+        self
+    }
+
+    /// See [`VerifyOptions::set_require_kid`].
+    pub fn require_kid(mut self, required: bool) -> Self {
+        self.options.set_require_kid(required);
+        self
+    }
+
+    /// See [`VerifyOptions::set_required_claims`].
+    pub fn required_claims(mut self, claims: &[&'static str]) -> Self {
+        self.options.set_required_claims(claims);
+        self
+    }
+
+    /// See [`VerifyOptions::set_require_typ`].
+    pub fn require_typ(mut self, typs: &[&str]) -> Self {
+        self.options.set_require_typ(typs);
+        self
+    }
+
+    /// See [`VerifyOptions::set_understood_crit`].
+    pub fn understood_crit(mut self, names: &[&str]) -> Self {
+        self.options.set_understood_crit(names);
+        self
+    }
+
+    /// See [`VerifyOptions::set_allowed_jku`].
+    pub fn allowed_jku(mut self, urls: &[&str]) -> Self {
+        self.options.set_allowed_jku(urls);
+        self
+    }
+
+    /// Fails with [`Error::NoAllowedAlgorithms`] if
+    /// [`algorithms`](Self::algorithms) was not called or was given an empty
+    /// list.
+    pub fn build(self) -> Result<VerifyOptions> {
+        match &self.options.allowed_algorithms {
+            Some(algs) if !algs.is_empty() => Ok(self.options),
+            _ => Err(Error::NoAllowedAlgorithms),
+        }
+    }
+}
+
+// Compared as durations since the epoch, so that dates too far in the future
+// for `SystemTime` don't overflow.
+fn since_epoch(t: SystemTime) -> Duration {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+fn is_after(t: SystemTime, date: NumericDate) -> bool {
+    since_epoch(t) > date.to_duration()
+}
+
+/// Decode and verify token.
+///
+/// The `alg`, `exp` and `nbf` fields are automatically checked.
+///
+/// Decoded segments of up to 512 bytes are kept on the stack. For a hot path,
+/// create the key, and the options if any, once and reuse them.
+pub fn verify<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    verify_with_options(token, k, &VerifyOptions::default())
+}
+
+/// Decode and verify token, then validate claims according to `options`.
+///
+/// The `alg` field is always checked.
+pub fn verify_with_options<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        k.verify(v, sig, &header.alg)
+    })?;
+    options.decode_and_check(header, payload)
+}
+
+/// Like [`verify_with_options`], but returns the header, the registered claims
+/// and the extra claims separately.
+pub fn verify_split<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<(Header, Claims<()>, ExtraClaims)> {
+    verify_with_options(token, k, options).map(HeaderAndClaims::into_parts)
+}
+
+/// Like [`verify_with_options`], but also returns the signing input, i.e.
+/// `base64url(header).base64url(payload)` exactly as it was verified, e.g. to
+/// hash it for an audit log.
+pub fn verify_with_signing_input<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<(HeaderAndClaims<ExtraClaims>, Vec<u8>)> {
+    let mut signing_input = Vec::new();
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        k.verify(v, sig, &header.alg)?;
+        signing_input = v.to_vec();
+        Ok(())
+    })?;
+    Ok((options.decode_and_check(header, payload)?, signing_input))
+}
+
+/// Like [`verify_with_options`], but only deserialize the claim named
+/// `claim_name`, e.g. `scope`, instead of the whole payload. The registered
+/// claims are still checked according to `options`.
+///
+/// Returns `None` if the claim is missing or `null`.
+pub fn verify_claim<T: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+    claim_name: &str,
+) -> Result<Option<T>> {
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        k.verify(v, sig, &header.alg)
+    })?;
+    let payload = decode_segment(payload, Segment::Payload)?;
+    options.parse_and_check::<serde::de::IgnoredAny>(header, &payload)?;
+    let seed = ClaimSeed {
+        name: claim_name,
+        claim: std::marker::PhantomData,
+    };
+    Ok(serde::de::DeserializeSeed::deserialize(
+        seed,
+        &mut serde_json::Deserializer::from_slice(&payload),
+    )?)
+}
+
+// The claim `name` of `token`, WITHOUT verifying the signature, e.g. to route
+// the token to its verifier. Only this claim is deserialized.
+#[cfg(feature = "remote-jwks")]
+pub(crate) fn unverified_claim<T: DeserializeOwned>(token: &[u8], name: &str) -> Result<Option<T>> {
+    let (_header, payload, _sig) = split_token(token)?;
+    let payload = decode_segment(payload, Segment::Payload)?;
+    let seed = ClaimSeed {
+        name,
+        claim: std::marker::PhantomData,
+    };
+    Ok(serde::de::DeserializeSeed::deserialize(
+        seed,
+        &mut serde_json::Deserializer::from_slice(&payload),
+    )?)
+}
+
+// Deserializes the value of the field `name` of an object, skipping the others.
+struct ClaimSeed<'a, T> {
+    name: &'a str,
+    claim: std::marker::PhantomData<T>,
+}
+
+impl<'de, T: DeserializeOwned> serde::de::DeserializeSeed<'de> for ClaimSeed<'_, T> {
+    type Value = Option<T>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> serde::de::Visitor<'de> for ClaimSeed<'_, T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a claims object")
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let mut claim = None;
+        while let Some(JsonKey(name)) = map.next_key()? {
+            if claim.is_none() && name == self.name {
+                claim = map.next_value::<Option<T>>()?;
+            } else {
+                map.next_value::<serde::de::IgnoredAny>()?;
+            }
+        }
+        Ok(claim)
+    }
+}
+
+/// Verify only the signature of `token`, and return its header.
+///
+/// The payload is not decoded, so it doesn't have to be JSON. Use this for
+/// JWS with arbitrary payloads. No claims are validated.
+pub fn verify_signature_only(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<Header> {
+    let (header, _payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        k.verify(v, sig, &header.alg)
+    })?;
+    Ok(header)
+}
+
+/// Like [`verify`], but the token is given as bytes, e.g. straight from a
+/// request header buffer.
+pub fn verify_bytes<ExtraClaims: DeserializeOwned>(
+    token: &[u8],
+    k: &dyn VerificationKey,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let claims = verify_only_bytes(token, k)?;
+    VerifyOptions::default().check(&claims.claims)?;
+    Ok(claims)
+}
+
+/// Decode and verify token, but do not check `exp` and `nbf`.
+///
+/// The `alg` field is still checked.
+pub fn verify_only<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    verify_only_bytes(token.as_bytes(), k)
+}
+
+/// Like [`verify_only`], but the token is given as bytes.
+pub fn verify_only_bytes<ExtraClaims: DeserializeOwned>(
+    token: &[u8],
+    k: &dyn VerificationKey,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload) =
+        verify_signature_with(token, &VerifyOptions::default(), |header, v, sig| {
+            k.verify(v, sig, &header.alg)
+        })?;
+    decode_claims(header, payload, false)
+}
+
+/// Verify a detached JWS with an unencoded payload (RFC 7797), i.e. a token
+/// of the form `header..signature` whose header has `"b64": false` and
+/// `"crit": ["b64"]`, and return its header.
+///
+/// The signing input is the encoded header, `.`, and `payload` as is, or as
+/// canonicalized by [`VerifyOptions::set_payload_canonicalization`]. The
+/// payload is not decoded and no claims are validated.
+pub fn verify_detached_unencoded(
+    token: &str,
+    payload: &[u8],
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<Header> {
+    let (header, header_b64, sig, unencoded) = decode_detached(token.as_bytes(), options)?;
+    if !unencoded {
+        return Err(Error::InvalidToken);
+    }
+
+    let canonical;
+    let payload = match options.canonicalize_payload {
+        Some(canonicalize) => {
+            canonical = canonicalize(payload)?;
+            &canonical[..]
+        }
+        None => payload,
+    };
+    let mut signing_input = Vec::with_capacity(header_b64.len() + 1 + payload.len());
+    signing_input.extend_from_slice(header_b64);
+    signing_input.push(b'.');
+    signing_input.extend_from_slice(payload);
+    k.verify(&signing_input, &sig, &header.alg)?;
+    Ok(header)
+}
+
+/// Verify a detached JWS, i.e. a token of the form `header..signature`, over
+/// the payload read from `payload`, and return its header.
+///
+/// The payload is streamed into the key, see
+/// [`VerificationKey::verify_reader`], so it can be e.g. a large file. It is
+/// base64url encoded on the fly, unless the header has `"b64": false` and
+/// `"crit": ["b64"]` (RFC 7797). The payload is not decoded and no claims are
+/// validated.
+pub fn verify_detached_reader(
+    token: &str,
+    mut payload: impl Read,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<Header> {
+    let (header, header_b64, sig, unencoded) = decode_detached(token.as_bytes(), options)?;
+    let prefix = header_b64.chain(&b"."[..]);
+    if unencoded {
+        k.verify_reader(&mut prefix.chain(payload), &sig, &header.alg)?;
+    } else {
+        let encoded = Base64UrlReader::new(&mut payload);
+        k.verify_reader(&mut prefix.chain(encoded), &sig, &header.alg)?;
+    }
+    Ok(header)
+}
+
+// Decode and check the header and signature of a detached JWS.
+//
+// Returns the header, the still encoded header, the signature, and whether the
+// payload is unencoded, i.e. `b64` is `false`.
+fn decode_detached<'a>(
+    token: &'a [u8],
+    options: &VerifyOptions,
+) -> Result<(Header, &'a [u8], SegmentBuf, bool)> {
+    options.check_len(token)?;
+    let (header_b64, empty, sig) = split_token(token)?;
+    if !empty.is_empty() {
+        return Err(Error::InvalidToken);
+    }
+    let mut header = decode_header_with(header_b64, options)?;
+    options.check_kid(&header)?;
+    options.normalize_alg(&mut header);
+    options.check_alg(&header)?;
+    options.check_jku(&header)?;
+    options.check_crit_with(&header, &["b64"])?;
+    let crit_b64 = header
+        .extra
+        .get("crit")
+        .and_then(Value::as_array)
+        .is_some_and(|crit| crit.iter().any(|name| name == "b64"));
+    let unencoded = match header.extra.get("b64") {
+        None | Some(Value::Bool(true)) => false,
+        Some(Value::Bool(false)) if crit_b64 => true,
+        _ => return Err(Error::InvalidToken),
+    };
+    let sig = decode_segment(sig, Segment::Signature)?;
+    Ok((header, header_b64, sig, unencoded))
+}
+
+// Reads `inner` as unpadded base64url.
+struct Base64UrlReader<R> {
+    inner: R,
+    // Read, but not yet encoded because not a multiple of 3 bytes.
+    pending: [u8; 3],
+    pending_len: usize,
+    encoded: String,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> Base64UrlReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pending: [0; 3],
+            pending_len: 0,
+            encoded: String::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Base64UrlReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.encoded.len() && !self.eof {
+            let mut chunk = [0; 3 * 1024];
+            chunk[..self.pending_len].copy_from_slice(&self.pending[..self.pending_len]);
+            let n = self.inner.read(&mut chunk[self.pending_len..])?;
+            let len = self.pending_len + n;
+            // Only the last chunk may be encoded with a partial group.
+            let whole = if n == 0 { len } else { len - len % 3 };
+            self.eof = n == 0;
+            self.pending_len = len - whole;
+            self.pending[..self.pending_len].copy_from_slice(&chunk[whole..len]);
+            self.encoded.clear();
+            self.pos = 0;
+            URL_SAFE_TRAILING_BITS.encode_string(&chunk[..whole], &mut self.encoded);
+        }
+        let n = buf.len().min(self.encoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.encoded.as_bytes()[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Decode and verify token with the first of `keys` that verifies its
+/// signature, then validate claims according to `options`.
+///
+/// Returns the index of the key that verified the token along with the
+/// token.
+pub fn verify_with_keys<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    keys: &[&dyn VerificationKey],
+    options: &VerifyOptions,
+) -> Result<(usize, HeaderAndClaims<ExtraClaims>)> {
+    let mut matched = 0;
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        let mut res = Err(Error::NoKey);
+        for (i, k) in keys.iter().enumerate() {
+            res = k.verify(v, sig, &header.alg);
+            if res.is_ok() {
+                matched = i;
+                break;
+            }
+        }
+        res
+    })?;
+    Ok((matched, options.decode_and_check(header, payload)?))
+}
+
+/// Decode token, select the key to verify it with by calling `select` with its
+/// header, then validate claims according to `options`.
+///
+/// `select` runs after the header is decoded and `alg`, `jku` and `crit` are
+/// checked, but before the signature is verified. Errors returned by `select`
+/// are propagated as is. The selected key can be e.g. a `&dyn VerificationKey`
+/// or an `Arc<dyn VerificationKey>`.
+pub fn verify_with<ExtraClaims, K, F>(
+    token: &str,
+    select: F,
+    options: &VerifyOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>>
+where
+    ExtraClaims: DeserializeOwned,
+    K: Deref,
+    K::Target: VerificationKey,
+    F: FnOnce(&Header) -> Result<K>,
+{
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |header, v, sig| {
+        select(header)?.verify(v, sig, &header.alg)
+    })?;
+    options.decode_and_check(header, payload)
+}
+
+// Decode the header and signature, and verify the signature with `verify`.
+//
+// Returns the header and the still encoded payload.
+fn verify_signature_with<'a>(
+    token: &'a [u8],
+    options: &VerifyOptions,
+    verify: impl FnOnce(&Header, &[u8], &[u8]) -> Result<()>,
+) -> Result<(Header, &'a [u8])> {
+    options.check_len(token)?;
+    let (header, payload, mut sig) = split_token(token)?;
+    let header_and_payload_len = header.len() + payload.len() + 1;
+
+    let mut header = decode_header_with(header, options)?;
+    options.check_kid(&header)?;
+    options.normalize_alg(&mut header);
+    options.check_alg(&header)?;
+    options.check_jku(&header)?;
+    options.check_crit(&header)?;
+    if options.tolerate_sig_padding {
+        while let Some(unpadded) = sig.strip_suffix(b"=") {
+            sig = unpadded;
+        }
+    }
+    let sig = decode_segment(sig, Segment::Signature)?;
+
+    verify(&header, &token[..header_and_payload_len], &sig)?;
+
+    Ok((header, payload))
+}
+
+fn decode_claims<ExtraClaims: DeserializeOwned>(
+    header: Header,
+    payload: &[u8],
+    allow_duplicate_keys: bool,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let claims: Claims<ExtraClaims> = parse_json(
+        &decode_segment(payload, Segment::Payload)?,
+        allow_duplicate_keys,
+    )?;
+
+    Ok(HeaderAndClaims { header, claims })
+}
+
+pub(crate) fn decode_header(header: &[u8]) -> Result<Header> {
+    Ok(serde_json::from_slice(&decode_segment(
+        header,
+        Segment::Header,
+    )?)?)
+}
+
+// Like `decode_header`, but duplicate keys are rejected unless allowed by
+// `options`.
+fn decode_header_with(header: &[u8], options: &VerifyOptions) -> Result<Header> {
+    parse_json(
+        &decode_segment(header, Segment::Header)?,
+        options.allow_duplicate_keys,
+    )
+}
+
+fn parse_json<T: DeserializeOwned>(json: &[u8], allow_duplicate_keys: bool) -> Result<T> {
+    if allow_duplicate_keys {
+        Ok(serde_json::from_slice(json)?)
+    } else {
+        json::from_slice_unique_keys(json)
+    }
+}
+
+// An object key, borrowed from the input unless it has escapes. Unlike
+// `Cow<str>`, which is always owned.
+struct JsonKey<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for JsonKey<'de> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for KeyVisitor {
+            type Value = JsonKey<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> std::result::Result<Self::Value, E> {
+                Ok(JsonKey(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                Ok(JsonKey(Cow::Owned(v.into())))
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+// Decoded segments this long or shorter don't allocate.
+type SegmentBuf = SmallVec<[u8; 512]>;
+
+fn decode_segment(segment: &[u8], which: Segment) -> Result<SegmentBuf> {
+    let mut buf = SegmentBuf::new();
+    buf.resize(base64::decoded_len_estimate(segment.len()), 0);
+    let len = URL_SAFE_TRAILING_BITS
+        .decode_slice(segment, &mut buf)
+        .map_err(|e| match e {
+            base64::DecodeSliceError::DecodeError(e) => Error::Base64(which, e),
+            // The buffer is as long as the estimate, which should never be
+            // too short. Don't panic on untrusted input if it is.
+            base64::DecodeSliceError::OutputSliceTooSmall => Error::InvalidToken,
+        })?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+// Split a compact JWS into header, payload and signature.
+//
+// Stops at the third separator, so tokens with lots of `.`s are rejected
+// without looking at all of them.
+fn split_token(token: &[u8]) -> Result<(&[u8], &[u8], &[u8])> {
+    let mut parts = token.splitn(4, |b| *b == b'.');
+
+    let header = parts.next().ok_or(Error::InvalidToken)?;
+    let payload = parts.next().ok_or(Error::InvalidToken)?;
+    let sig = parts.next().ok_or(Error::InvalidToken)?;
+    if parts.next().is_some() {
+        return Err(Error::InvalidToken);
+    }
+    Ok((header, payload, sig))
+}
+
+/// Segments of a compact JWS, as returned by [`inspect`].
+#[derive(Debug)]
+pub struct TokenParts<'a> {
+    /// The decoded header. NOT verified.
+    pub header: Header,
+    /// The base64url encoded header segment.
+    pub header_b64: &'a str,
+    /// The base64url encoded payload segment.
+    pub payload_b64: &'a str,
+    /// The base64url encoded signature segment.
+    pub signature_b64: &'a str,
+    token: &'a str,
+}
+
+impl<'a> TokenParts<'a> {
+    /// The signing input, i.e. `header_b64.payload_b64`.
+    pub fn signing_input(&self) -> &'a [u8] {
+        let len = self.header_b64.len() + 1 + self.payload_b64.len();
+        &self.token.as_bytes()[..len]
+    }
+}
+
+/// Check that `token` looks like a compact JWS, without verifying it.
+///
+/// The token must have exactly three non-empty base64url segments, and the
+/// header must be a JSON object with an `alg`. Five segment tokens, i.e.
+/// compact JWEs, are rejected with [`Error::UnexpectedJwe`].
+pub fn inspect(token: &str) -> Result<TokenParts<'_>> {
+    if token.splitn(6, '.').count() == 5 {
+        return Err(Error::UnexpectedJwe);
+    }
+    let (header, payload, sig) = split_token(token.as_bytes())?;
+    fn is_base64url(segment: &[u8]) -> bool {
+        !segment.is_empty()
+            && segment
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_')
+    }
+    if !(is_base64url(header) && is_base64url(payload) && is_base64url(sig)) {
+        return Err(Error::InvalidToken);
+    }
+
+    let header_b64 = &token[..header.len()];
+    let payload_b64 = &token[header.len() + 1..][..payload.len()];
+    let signature_b64 = &token[token.len() - sig.len()..];
+
+    let header = decode_header(header)?;
+
+    Ok(TokenParts {
+        header,
+        header_b64,
+        payload_b64,
+        signature_b64,
+        token,
+    })
+}
+
+/// Whether a compact token is signed or encrypted, see [`classify`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A JWS, i.e. a signed token.
+    Jws { alg: String },
+    /// A JWE, i.e. an encrypted token.
+    Jwe { alg: String, enc: String },
+}
+
+/// Tell JWSs from JWEs by the number of segments and the header, e.g. to
+/// route tokens to verification or decryption. Nothing is verified or
+/// decrypted.
+///
+/// Fails with [`Error::InvalidToken`] if the header does not match the number
+/// of segments, i.e. a JWS with `enc` or a JWE without.
+pub fn classify(token: &str) -> Result<TokenKind> {
+    let segments = token.splitn(6, '.').count();
+    if segments != 3 && segments != 5 {
+        return Err(Error::InvalidToken);
+    }
+    let header = token.split('.').next().ok_or(Error::InvalidToken)?;
+    let header = decode_header(header.as_bytes())?;
+    let alg = header.alg.into_owned();
+    match (segments, header.extra.get("enc")) {
+        (3, None) => Ok(TokenKind::Jws { alg }),
+        (5, Some(Value::String(enc))) => Ok(TokenKind::Jwe {
+            alg,
+            enc: enc.clone(),
+        }),
+        _ => Err(Error::InvalidToken),
+    }
+}
+
+/// Decode token.
+///
+/// No verification or validation is performed.
+pub fn decode_without_verify<ExtraClaims: DeserializeOwned>(
+    token: &str,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload, _sig) = split_token(token.as_bytes())?;
+
+    decode_claims(decode_header(header)?, payload, true)
+}
+
+/// **Dangerous**: decode token and validate claims according to `options`,
+/// WITHOUT verifying the signature.
+///
+/// Only use this for tokens from a peer that is already authenticated, e.g.
+/// by mTLS, and trusted to have verified the token, e.g. a sidecar. Anyone
+/// can forge a token that passes this.
+///
+/// Everything that [`verify_with_options`] checks is checked, except the
+/// signature. The signature must still be well formed base64url.
+pub fn parse_trusted<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    options: &VerifyOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload) = verify_signature_with(token.as_bytes(), options, |_, _, _| Ok(()))?;
+    options.decode_and_check(header, payload)
+}
+
+/// A key that can produce JWS signatures.
+///
+/// Besides signing tokens with [`sign`](crate::sign), keys can sign arbitrary
+/// data with [`sign`](Self::sign) or
+/// [`sign_base64url`](Self::sign_base64url), e.g. webhook bodies. Signatures
+/// are in the JWS format of the algorithm, e.g. `r || s` for ECDSA.
+pub trait SigningKey {
+    // A signing key has a rigid algorithm.
+    fn alg(&self) -> &'static str;
+
+    /// [`alg`](Self::alg) as an [`Algorithm`].
+    fn algorithm(&self) -> Result<Algorithm> {
+        self.alg().parse()
+    }
+
+    /// Optional key id. If it is present, then it is automatically set in
+    /// header claims.
+    fn kid(&self) -> Option<&str> {
+        None
+    }
+
+    /// Sign `v` with [`alg`](Self::alg).
+    // Es256 and eddsa signatures are 64-byte long.
+    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>>;
+
+    /// Like [`sign`](Self::sign), but returns the signature as unpadded
+    /// base64url.
+    fn sign_base64url(&self, v: &[u8]) -> Result<String> {
+        Ok(URL_SAFE_TRAILING_BITS.encode(self.sign(v)?))
+    }
+}
+
+/// A key that can verify JWS signatures.
+///
+/// Like [`SigningKey`], this can be used for signatures over arbitrary data.
+///
+/// Private and HMAC keys implement this too. The verify-only types are the
+/// `*PublicKey`s, including [`SomePublicKey`] and keys loaded from JWKs, see
+/// [`can_sign`](Self::can_sign).
+pub trait VerificationKey {
+    /// Verify that `sig` is a valid `alg` signature of `v`.
+    // `alg` is passed in because HMAC and RSA verification keys can verify
+    // signatures generated with multiple algorithms.
+    fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()>;
+
+    /// Like [`verify`](Self::verify), but `sig` is unpadded base64url, as
+    /// returned by [`SigningKey::sign_base64url`].
+    fn verify_base64url(&self, v: &[u8], sig: &str, alg: &str) -> Result<()> {
+        let sig = decode_segment(sig.as_bytes(), Segment::Signature)?;
+        self.verify(v, &sig, alg)
+    }
+
+    /// Like [`verify`](Self::verify), but with a typed algorithm.
+    fn verify_with_algorithm(&self, v: &[u8], sig: &[u8], alg: Algorithm) -> Result<()> {
+        self.verify(v, sig, alg.as_str())
+    }
+
+    /// Like [`verify`](Self::verify), but `v` is read from `input`, e.g. a
+    /// detached payload that doesn't fit in memory.
+    ///
+    /// The default implementation reads all of `input` into memory. RSA,
+    /// ECDSA and HMAC keys stream it instead.
+    fn verify_reader(&self, input: &mut dyn Read, sig: &[u8], alg: &str) -> Result<()> {
+        let mut v = Vec::new();
+        input.read_to_end(&mut v)?;
+        self.verify(&v, sig, alg)
+    }
+
+    /// The `alg` values this key can verify signatures for.
+    ///
+    /// The default implementation returns an empty list, meaning unknown.
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Whether this key could also sign, i.e. is a private or HMAC key.
+    ///
+    /// Defaults to `false`. Keys that can sign should override this.
+    fn can_sign(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! impl_key_traits_for_pointer {
+    ($($pointer:ident)*) => {$(
+        impl<K: SigningKey + ?Sized> SigningKey for $pointer<K> {
+            #[inline]
+            fn alg(&self) -> &'static str {
+                (**self).alg()
+            }
+
+            #[inline]
+            fn algorithm(&self) -> Result<Algorithm> {
+                (**self).algorithm()
+            }
+
+            #[inline]
+            fn kid(&self) -> Option<&str> {
+                (**self).kid()
+            }
+
+            #[inline]
+            fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+                (**self).sign(v)
+            }
+        }
+
+        impl<K: VerificationKey + ?Sized> VerificationKey for $pointer<K> {
+            #[inline]
+            fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+                (**self).verify(v, sig, alg)
+            }
+
+            #[inline]
+            fn verify_with_algorithm(&self, v: &[u8], sig: &[u8], alg: Algorithm) -> Result<()> {
+                (**self).verify_with_algorithm(v, sig, alg)
+            }
+
+            #[inline]
+            fn verify_reader(&self, input: &mut dyn Read, sig: &[u8], alg: &str) -> Result<()> {
+                (**self).verify_reader(input, sig, alg)
+            }
+
+            #[inline]
+            fn accepted_algorithms(&self) -> Vec<&'static str> {
+                (**self).accepted_algorithms()
+            }
+
+            #[inline]
+            fn can_sign(&self) -> bool {
+                (**self).can_sign()
+            }
+        }
+    )*};
+}
+
+impl_key_traits_for_pointer!(Box Arc);
+
+pub trait PublicKeyToJwk {
+    fn public_key_to_jwk(&self) -> Result<Jwk>;
+
+    /// Like [`public_key_to_jwk`](Self::public_key_to_jwk), but with the
+    /// given `kid` and `use`, and without `alg` unless `include_alg`.
+    fn public_key_to_jwk_with(
+        &self,
+        kid: Option<String>,
+        use_: Option<&str>,
+        include_alg: bool,
+    ) -> Result<Jwk> {
+        let mut jwk = self.public_key_to_jwk()?;
+        jwk.kid = kid;
+        jwk.use_ = use_.map(Into::into);
+        if !include_alg {
+            jwk.alg = None;
+        }
+        Ok(jwk)
+    }
+}
+
+pub trait PrivateKeyToJwk {
+    fn private_key_to_jwk(&self) -> Result<Jwk>;
+}
+
+/// A segment of a compact JWS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Header,
+    Payload,
+    Signature,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Header => "header".fmt(f),
+            Segment::Payload => "payload".fmt(f),
+            Segment::Signature => "signature".fmt(f),
+        }
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    InvalidToken,
+    VerificationError,
+    AlgMismatch,
+    /// The `alg` of the token is for a different type of key than the
+    /// verification key, e.g. `HS256` for an RSA key.
+    AlgorithmKeyMismatch,
+    NoKid,
+    NoKey,
+    /// Several keys in the JWK Set match both the `kid` and the `alg` of the
+    /// token.
+    AmbiguousKey,
+    /// The `typ` header is not one of the required values.
+    InvalidTyp,
+    /// The `alg` of the token is not in the allowlist of the verification
+    /// options.
+    AlgorithmNotAllowed,
+    /// The `iss` claim is not the required issuer.
+    InvalidIssuer,
+    /// The `aud` claim does not contain the required audience.
+    InvalidAudience,
+    /// [`VerifyOptionsBuilder::build`] was called without algorithms.
+    NoAllowedAlgorithms,
+    /// The `jku` header is not in the allowlist of the verification options.
+    UntrustedJku,
+    /// The `crit` header is malformed or lists a parameter that is not
+    /// understood, see [`VerifyOptions::set_understood_crit`].
+    UnsupportedCrit,
+    Expired,
+    /// The token is not valid yet , i.e. `nbf` check failed.
+    Before,
+    /// The token was issued too long ago, i.e. `iat` is older than the
+    /// configured max token age.
+    TokenTooOld,
+    /// The token's declared lifetime, i.e. `exp - iat`, is longer than the
+    /// configured max lifetime.
+    LifetimeTooLong,
+    /// The token looks like a compact JWE (five segments) rather than a JWS.
+    UnexpectedJwe,
+    /// A claim required by the verification options is missing.
+    MissingClaim(&'static str),
+    /// A JWK Set has more keys than allowed.
+    TooManyKeys,
+    /// The token is rejected because of its shape: it's longer than allowed
+    /// by [`VerifyOptions::set_max_token_len`], or the header or payload has a
+    /// JSON object with duplicate member names, see
+    /// [`VerifyOptions::set_allow_duplicate_keys`].
+    MalformedToken,
+    /// The current time is before the unix epoch, e.g. the clock of a device
+    /// has not been synced yet.
+    ClockError,
+    UnsupportedOrInvalidKey,
+    /// The certificate is expired or not valid yet.
+    CertificateExpired,
+    /// The key is allowed for verification, but too small for signing under
+    /// [`KeyPolicy::require_strong_signing_keys`].
+    KeyTooWeakForSigning,
+    Utf8(FromUtf8Error),
+    IoError(std::io::Error),
+    OpenSsl(ErrorStack),
+    /// OpenSSL runs in FIPS mode and rejected the operation, most likely
+    /// because the algorithm, key size or parameters are not approved. Only
+    /// reported with OpenSSL 3 and its FIPS provider enabled by default.
+    FipsNotAllowed(ErrorStack),
+    SerdeJson(serde_json::Error),
+    Decode(base64::DecodeError),
+    /// A token segment is not valid base64url.
+    Base64(Segment, base64::DecodeError),
+    #[cfg(feature = "remote-jwks")]
+    Reqwest(reqwest::Error),
+    /// Fetching a remote JWK Set timed out.
+    #[cfg(feature = "remote-jwks")]
+    Timeout,
+    /// The first fetch of a remote JWK Set, which this request waited for,
+    /// failed.
+    #[cfg(feature = "remote-jwks")]
+    JwksUnavailable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IoError(e) => e.fmt(f),
+            Error::OpenSsl(e) => e.fmt(f),
+            Error::FipsNotAllowed(e) => write!(f, "not allowed by OpenSSL FIPS policy: {}", e),
+            Error::SerdeJson(e) => e.fmt(f),
+            Error::Decode(e) => e.fmt(f),
+            Error::Base64(segment, e) => write!(f, "invalid base64url in token {}: {}", segment, e),
+            #[cfg(feature = "remote-jwks")]
+            Error::Reqwest(e) => e.fmt(f),
+            Error::Utf8(e) => e.fmt(f),
+            Error::VerificationError => "failed to verify signature".fmt(f),
+            Error::AlgMismatch => {
+                "the alg field in JWT header is different from what the verification key uses"
+                    .fmt(f)
+            }
+            Error::AlgorithmKeyMismatch => {
+                "the alg field in JWT header is for a different key type".fmt(f)
+            }
+            Error::InvalidToken => "the token not in a valid format".fmt(f),
+            Error::InvalidTyp => "the typ field in JWT header is not allowed".fmt(f),
+            Error::AlgorithmNotAllowed => "the alg field in JWT header is not allowed".fmt(f),
+            Error::InvalidIssuer => "the token has a different issuer".fmt(f),
+            Error::InvalidAudience => "the token is not for this audience".fmt(f),
+            Error::NoAllowedAlgorithms => "no algorithms are allowed".fmt(f),
+            Error::UntrustedJku => "the jku field in JWT header is not trusted".fmt(f),
+            Error::UnsupportedCrit => "the crit field in JWT header is not supported".fmt(f),
+            Error::NoKid => "the kid field is missing from the JWT header".fmt(f),
+            Error::NoKey => "no key in the JWK Set matches the kid".fmt(f),
+            Error::AmbiguousKey => "several keys in the JWK Set match the kid and alg".fmt(f),
+            Error::UnsupportedOrInvalidKey => "unsupported or invalid key".fmt(f),
+            Error::KeyTooWeakForSigning => "the key is too small for signing".fmt(f),
+            Error::Expired => "token expired (exp check failed)".fmt(f),
+            Error::Before => "token is not valid yet (nbf check failed)".fmt(f),
+            Error::TokenTooOld => "token was issued too long ago (iat check failed)".fmt(f),
+            Error::LifetimeTooLong => "token lifetime is too long (exp - iat)".fmt(f),
+            Error::UnexpectedJwe => "the token is a JWE, not a JWS".fmt(f),
+            Error::MissingClaim(claim) => write!(f, "the {} claim is missing", claim),
+            Error::TooManyKeys => "the JWK set has too many keys".fmt(f),
+            Error::MalformedToken => "malformed token".fmt(f),
+            Error::CertificateExpired => "the certificate is expired or not valid yet".fmt(f),
+            Error::ClockError => "the system clock is before the unix epoch".fmt(f),
+            #[cfg(feature = "remote-jwks")]
+            Error::Timeout => "timed out fetching the JWK set".fmt(f),
+            #[cfg(feature = "remote-jwks")]
+            Error::JwksUnavailable => "the JWK set could not be fetched".fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::OpenSsl(e) => Some(e),
+            Error::FipsNotAllowed(e) => Some(e),
+            Error::SerdeJson(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            Error::Base64(_, e) => Some(e),
+            Error::Utf8(e) => Some(e),
+            #[cfg(feature = "remote-jwks")]
+            Error::Reqwest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(e: std::io::Error) -> Error {
+        Error::IoError(e)
+    }
+}
+
+// Reasons reported by the OpenSSL 3 FIPS provider when an operation or
+// parameter is not allowed in FIPS mode.
+const FIPS_REASONS: &[&str] = &[
+    "digest not allowed",
+    "invalid key length",
+    "invalid salt length",
+    "key size too small",
+];
+
+fn is_fips_rejection(reason: &str) -> bool {
+    let reason = reason.to_ascii_lowercase();
+    reason.contains("fips") || FIPS_REASONS.contains(&&*reason)
+}
+
+// Whether the default library context fetches algorithms from the FIPS
+// provider. Only OpenSSL 3 has one.
+#[cfg(ossl300)]
+fn fips_enabled() -> bool {
+    unsafe { openssl_sys::EVP_default_properties_is_fips_enabled(std::ptr::null_mut()) == 1 }
+}
+
+#[cfg(not(ossl300))]
+fn fips_enabled() -> bool {
+    false
+}
+
+fn classify_error_stack(e: ErrorStack, fips: bool) -> Error {
+    if fips
+        && e.errors()
+            .iter()
+            .any(|e| e.reason().is_some_and(is_fips_rejection))
+    {
+        Error::FipsNotAllowed(e)
+    } else {
+        Error::OpenSsl(e)
+    }
+}
+
+impl From<ErrorStack> for Error {
+    fn from(e: ErrorStack) -> Error {
+        classify_error_stack(e, fips_enabled())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    #[inline]
+    fn from(e: serde_json::Error) -> Error {
+        Error::SerdeJson(e)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    #[inline]
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    #[inline]
+    fn from(e: FromUtf8Error) -> Self {
+        Error::Utf8(e)
+    }
+}
+
+#[cfg(feature = "remote-jwks")]
+impl From<reqwest::Error> for Error {
+    #[inline]
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout
+        } else {
+            Error::Reqwest(e)
+        }
     }
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+
+    use super::*;
+
+    #[test]
+    fn signing_and_verification() -> Result<()> {
+        let mut claims = HeaderAndClaims::new_dynamic();
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        claims
+            .set_exp_from_now(Duration::from_secs(3))
+            .set_nbf_from_now(Duration::from_secs(1))
+            .set_iss("me")
+            .set_sub("you")
+            .add_aud("him")
+            .add_aud("her")
+            .set_jti("jti")
+            .set_kid("kid")
+            .set_iat_now()
+            .insert("foo", "bar")
+            .insert("baz", 9);
+        let token = sign(&mut claims, &k)?;
+
+        decode_without_verify::<Map<String, Value>>(&token)?;
+
+        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
+        assert!(verify_only::<Map<String, Value>>(&token, &k).is_ok());
+        std::thread::sleep(Duration::from_secs(2));
+        assert!(verify::<Map<String, Value>>(&token, &k).is_ok());
+        assert!(verify::<Map<String, Value>>(&token, &k1).is_err());
+        std::thread::sleep(Duration::from_secs(2));
+        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
+        assert!(verify_only::<Map<String, Value>>(&token, &k).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn header_setters() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims
+            .set_typ("JWT")
+            .set_cty("application/example+json")
+            .set_kid("kid")
+            .insert_header("z", 1)
+            .insert_header("a", 2);
+        let token = sign(&mut claims, &k)?;
+        let header = URL_SAFE_TRAILING_BITS.decode(token.split('.').next().unwrap())?;
+        assert_eq!(
+            std::str::from_utf8(&header).unwrap(),
+            r#"{"typ":"JWT","alg":"ES256","kid":"kid","cty":"application/example+json","a":2,"z":1}"#
+        );
+
+        let verified = verify::<Map<String, Value>>(&token, &k)?;
+        assert_eq!(
+            verified.header().cty.as_deref(),
+            Some("application/example+json")
+        );
+        assert_eq!(verified.header().extra["z"], 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn header_serialization_is_stable() -> Result<()> {
+        let mut header = Header {
+            typ: Some("JWT".into()),
+            alg: "ES256".into(),
+            kid: Some("kid".into()),
+            ..Default::default()
+        };
+        header.extra.insert("zip".into(), "DEF".into());
+        header.extra.insert("b64".into(), false.into());
+        header.extra.insert("crit".into(), vec!["b64"].into());
+        // Registered names in `extra` are not duplicated.
+        header.extra.insert("alg".into(), "none".into());
+        assert_eq!(
+            serde_json::to_string(&header)?,
+            r#"{"typ":"JWT","alg":"ES256","kid":"kid","b64":false,"crit":["b64"],"zip":"DEF"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detached_unencoded() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let detached = |header: Value, signed_payload: &[u8]| -> Result<String> {
+            let header_b64 = URL_SAFE_TRAILING_BITS.encode(header.to_string());
+            let mut signing_input = format!("{}.", header_b64).into_bytes();
+            signing_input.extend_from_slice(signed_payload);
+            Ok(format!(
+                "{}..{}",
+                header_b64,
+                k.sign_base64url(&signing_input)?
+            ))
+        };
+        let header = serde_json::json!({"alg": "ES256", "b64": false, "crit": ["b64"]});
+        let token = detached(header.clone(), br#"{"a":1,"b":2}"#)?;
+        let mut options = VerifyOptions::new();
+        verify_detached_unencoded(&token, br#"{"a":1,"b":2}"#, &k, &options)?;
+        assert!(verify_detached_unencoded(&token, br#"{"b":2,"a":1}"#, &k, &options).is_err());
+        // Embedded payloads are not accepted by this path, and `b64` is not
+        // understood by the normal one.
+        assert!(verify::<Value>(&token, &k).is_err());
+
+        // A stand-in for JCS, which sorts object keys.
+        options.set_payload_canonicalization(|payload| {
+            let v: Value = serde_json::from_slice(payload)?;
+            Ok(serde_json::to_vec(&v)?)
+        });
+        verify_detached_unencoded(&token, br#"{ "b": 2, "a": 1 }"#, &k, &options)?;
+
+        let no_crit = detached(
+            serde_json::json!({"alg": "ES256", "b64": false}),
+            br#"{"a":1,"b":2}"#,
+        )?;
+        assert!(matches!(
+            verify_detached_unencoded(&no_crit, br#"{"a":1,"b":2}"#, &k, &options),
+            Err(Error::InvalidToken)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn verify_single_claim() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::with_claims(serde_json::json!({
+            "scope": "read write",
+            "roles": ["admin"],
+            "nothing": null,
+        }));
+        claims.set_sub("you");
+        let token = sign(&mut claims, &k)?;
+        let mut options = VerifyOptions::new();
+
+        let scope: Option<String> = verify_claim(&token, &k, &options, "scope")?;
+        assert_eq!(scope.as_deref(), Some("read write"));
+        let roles: Option<Vec<String>> = verify_claim(&token, &k, &options, "roles")?;
+        assert_eq!(roles, Some(vec!["admin".into()]));
+        let sub: Option<String> = verify_claim(&token, &k, &options, "sub")?;
+        assert_eq!(sub.as_deref(), Some("you"));
+        assert_eq!(
+            verify_claim::<String>(&token, &k, &options, "nothing")?,
+            None
+        );
+        assert_eq!(
+            verify_claim::<String>(&token, &k, &options, "missing")?,
+            None
+        );
+        assert!(verify_claim::<u64>(&token, &k, &options, "scope").is_err());
+
+        options.set_issuer("issuer");
+        assert!(matches!(
+            verify_claim::<String>(&token, &k, &options, "scope"),
+            Err(Error::MissingClaim("iss"))
+        ));
+        claims.claims_mut().exp = Some(NumericDate::from_secs(1));
+        let expired = sign(&mut claims, &k)?;
+        assert!(matches!(
+            verify_claim::<String>(&expired, &k, &VerifyOptions::new(), "scope"),
+            Err(Error::Expired)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_keys() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = |header: &str, payload: &str| -> Result<String> {
+            let mut token = format!(
+                "{}.{}",
+                URL_SAFE_TRAILING_BITS.encode(header),
+                URL_SAFE_TRAILING_BITS.encode(payload),
+            );
+            let sig = k.sign_base64url(token.as_bytes())?;
+            token.push('.');
+            token.push_str(&sig);
+            Ok(token)
+        };
+        let header = r#"{"alg":"ES256"}"#;
+        let mut options = VerifyOptions::new();
+
+        let dup_exp = token(header, r#"{"exp":1,"sub":"a","exp":99999999999}"#)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&dup_exp, &k, &options),
+            Err(Error::MalformedToken)
+        ));
+        assert!(matches!(
+            verify_only::<Map<String, Value>>(&dup_exp, &k),
+            Err(Error::MalformedToken)
+        ));
+        let nested = token(header, r#"{"a":[{"b":1,"b":2}]}"#)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&nested, &k, &options),
+            Err(Error::MalformedToken)
+        ));
+        let dup_alg = token(r#"{"alg":"ES256","alg":"none"}"#, "{}")?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&dup_alg, &k, &options),
+            Err(Error::MalformedToken)
+        ));
+        // Escaped keys are compared unescaped.
+        let escaped = token(header, r#"{"sub":"a","s\u0075b":"b"}"#)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&escaped, &k, &options),
+            Err(Error::MalformedToken)
+        ));
+        verify_with_options::<Map<String, Value>>(
+            &token(header, r#"{"a":{"a":1}}"#)?,
+            &k,
+            &options,
+        )?;
+
+        options.set_allow_duplicate_keys(true);
+        let verified = verify_with_options::<Map<String, Value>>(&nested, &k, &options)?;
+        assert_eq!(verified.claims().extra["a"][0]["b"], 2);
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+    #[test]
+    fn require_kid() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let mut options = VerifyOptions::new();
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        options.set_require_kid(true);
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::NoKid)
+        ));
 
-    use super::*;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_kid("k");
+        let token = sign(&mut claims, &k)?;
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        Ok(())
+    }
 
     #[test]
-    fn signing_and_verification() -> Result<()> {
+    fn detached_reader() -> Result<()> {
+        // Returns at most 7 bytes at a time.
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(7).min(self.0.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        fn check(k: &(impl SigningKey + VerificationKey), payload: &[u8]) -> Result<()> {
+            let options = VerifyOptions::new();
+            let token = sign_raw_payload(&mut Header::default(), payload, k)?;
+            let mut parts: Vec<&str> = token.split('.').collect();
+            parts[1] = "";
+            let detached = parts.join(".");
+            verify_detached_reader(&detached, Trickle(payload), k, &options)?;
+            verify_detached_reader(&detached, payload, k, &options)?;
+            assert!(verify_detached_reader(&detached, &b"other"[..], k, &options).is_err());
+            // Not detached.
+            assert!(
+                payload.is_empty()
+                    || matches!(
+                        verify_detached_reader(&token, payload, k, &options),
+                        Err(Error::InvalidToken)
+                    )
+            );
+
+            let header = serde_json::json!({"alg": k.alg(), "b64": false, "crit": ["b64"]});
+            let header_b64 = URL_SAFE_TRAILING_BITS.encode(header.to_string());
+            let mut signing_input = format!("{}.", header_b64).into_bytes();
+            signing_input.extend_from_slice(payload);
+            let unencoded = format!("{}..{}", header_b64, k.sign_base64url(&signing_input)?);
+            verify_detached_reader(&unencoded, Trickle(payload), k, &options)?;
+            assert!(
+                verify_detached_reader(&detached, Trickle(&signing_input), k, &options).is_err()
+            );
+            Ok(())
+        }
+
+        let payload: Vec<u8> = (0..10_000u32).map(|i| (i * 7) as u8).collect();
+        let ec = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let rsa = crate::rsa::RsaPrivateKey::generate(2048, crate::rsa::RsaAlgorithm::PS256)?;
+        let hmac = crate::hmac::HmacKey::generate(crate::hmac::HmacAlgorithm::HS256)?;
+        let ed = crate::eddsa::Ed25519PrivateKey::generate()?;
+        for len in [0, 1, 2, 3, 4, payload.len()] {
+            check(&ec, &payload[..len])?;
+            check(&rsa, &payload[..len])?;
+            check(&hmac, &payload[..len])?;
+            check(&ed, &payload[..len])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn classify_tokens() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let jws = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        assert_eq!(
+            classify(&jws)?,
+            TokenKind::Jws {
+                alg: "ES256".into()
+            }
+        );
+
+        let jwe_header = URL_SAFE_TRAILING_BITS.encode(r#"{"alg":"RSA-OAEP","enc":"A256GCM"}"#);
+        let jwe = format!("{}.a.b.c.d", jwe_header);
+        assert_eq!(
+            classify(&jwe)?,
+            TokenKind::Jwe {
+                alg: "RSA-OAEP".into(),
+                enc: "A256GCM".into()
+            }
+        );
+        assert!(matches!(
+            classify(&format!("{}.a.b", jwe_header)),
+            Err(Error::InvalidToken)
+        ));
+        let (header, rest) = jws.split_once('.').unwrap();
+        assert!(matches!(
+            classify(&format!("{}.{}.d.e", header, rest)),
+            Err(Error::InvalidToken)
+        ));
+        assert!(classify(&format!("{}.x", jws)).is_err());
+        assert!(classify("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn signing_input() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("you"), &k)?;
+        let (claims, input) =
+            verify_with_signing_input::<Map<String, Value>>(&token, &k, &VerifyOptions::new())?;
+        assert_eq!(claims.claims().sub(), Some("you"));
+        assert_eq!(input, token.rsplit_once('.').unwrap().0.as_bytes());
+
+        let other = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        assert!(verify_with_signing_input::<Value>(&token, &other, &VerifyOptions::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn trusted() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let other = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims
+            .set_iss("sidecar")
+            .set_exp_from_now(Duration::from_secs(60));
+        let token = sign(&mut claims, &other)?;
+        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
+
+        let mut options = VerifyOptions::new();
+        options.set_issuer("sidecar");
+        let parsed = parse_trusted::<Map<String, Value>>(&token, &options)?;
+        assert_eq!(parsed.claims().iss(), Some("sidecar"));
+
+        options.set_issuer("someone else");
+        assert!(matches!(
+            parse_trusted::<Map<String, Value>>(&token, &options),
+            Err(Error::InvalidIssuer)
+        ));
+        claims.claims_mut().exp = Some(NumericDate::from_secs(1));
+        let expired = sign(&mut claims, &other)?;
+        assert!(matches!(
+            parse_trusted::<Map<String, Value>>(&expired, &VerifyOptions::new()),
+            Err(Error::Expired)
+        ));
+        assert!(matches!(
+            parse_trusted::<Map<String, Value>>(&format!("{}!", token), &VerifyOptions::new()),
+            Err(Error::Base64(Segment::Signature, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn crit() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = |crit: Value| -> Result<String> {
+            let mut claims = HeaderAndClaims::new_dynamic();
+            claims
+                .insert_header("vnd.example.policy", "strict")
+                .insert_header("crit", crit);
+            sign(&mut claims, &k)
+        };
+        let mut options = VerifyOptions::new();
+        let vendor = token(serde_json::json!(["vnd.example.policy"]))?;
+        assert!(matches!(
+            verify_with_options::<Value>(&vendor, &k, &options),
+            Err(Error::UnsupportedCrit)
+        ));
+        assert!(matches!(
+            verify_only::<Value>(&vendor, &k),
+            Err(Error::UnsupportedCrit)
+        ));
+
+        options.set_understood_crit(&["vnd.example.policy", "vnd.example.other", "kid"]);
+        verify_with_options::<Value>(&vendor, &k, &options)?;
+        for bad in [
+            serde_json::json!(["vnd.example.policy", "vnd.example.unknown"]),
+            // Not in the header.
+            serde_json::json!(["vnd.example.other"]),
+            serde_json::json!(["kid"]),
+            serde_json::json!([]),
+            serde_json::json!("vnd.example.policy"),
+            serde_json::json!([1]),
+        ] {
+            assert!(matches!(
+                verify_with_options::<Value>(&token(bad)?, &k, &options),
+                Err(Error::UnsupportedCrit)
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn header_round_trip() -> Result<()> {
+        let json = r#"{"typ":"JWT","alg":"ES256","kid":"kid","x-vendor":{"a":[1,2]},"b64":false}"#;
+        let header: Header = serde_json::from_str(json)?;
+        assert_eq!(header.extra["x-vendor"], serde_json::json!({"a": [1, 2]}));
+        let reserialized = serde_json::to_string(&header)?;
+        assert_eq!(serde_json::from_str::<Header>(&reserialized)?, header);
+
+        // Re-sign a verified token with the same header.
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
         let mut claims = HeaderAndClaims::new_dynamic();
+        *claims.header_mut() = header;
+        let token = sign(&mut claims, &k)?;
+        let verified = verify::<Map<String, Value>>(&token, &k)?;
+        let mut reissued = HeaderAndClaims::new_dynamic();
+        *reissued.header_mut() = verified.header().clone();
+        let token1 = sign(&mut reissued, &k)?;
+        let verified1 = verify::<Map<String, Value>>(&token1, &k)?;
+        assert_eq!(verified1.header(), verified.header());
+        assert_eq!(verified1.header().extra["x-vendor"]["a"][1], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn claim_accessors() -> Result<()> {
         let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
-        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
         claims
-            .set_exp_from_now(Duration::from_secs(3))
-            .set_nbf_from_now(Duration::from_secs(1))
             .set_iss("me")
             .set_sub("you")
             .add_aud("him")
-            .add_aud("her")
-            .set_jti("jti")
-            .set_kid("kid")
-            .set_iat_now()
-            .insert("foo", "bar")
-            .insert("baz", 9);
+            .add_aud("her");
+        claims.claims_mut().exp = Some(NumericDate::from_secs(4102444800));
         let token = sign(&mut claims, &k)?;
+        let verified = verify::<Map<String, Value>>(&token, &k)?;
+        assert_eq!(verified.iss(), Some("me"));
+        assert_eq!(verified.sub(), Some("you"));
+        assert_eq!(verified.exp(), Some(4102444800));
+        assert_eq!(verified.aud(), ["him", "her"]);
+
+        let claims: Claims<Map<String, Value>> = serde_json::from_str(r#"{"aud":"him"}"#)?;
+        assert_eq!(claims.aud(), ["him"]);
+        assert_eq!(claims.sub(), None);
+        assert_eq!(claims.exp(), None);
+        let claims: Claims<Map<String, Value>> = serde_json::from_str("{}")?;
+        assert!(claims.aud().is_empty());
+        assert_eq!(claims.time_until_expiry(SystemTime::now()), None);
+
+        let exp = UNIX_EPOCH + Duration::from_secs(4102444800);
+        assert_eq!(
+            verified.time_until_expiry(exp - Duration::from_secs(30)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            verified.time_until_expiry(exp + Duration::from_secs(30)),
+            Some(Duration::ZERO)
+        );
 
-        decode_without_verify::<Map<String, Value>>(&token)?;
+        Ok(())
+    }
 
-        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
-        assert!(verify_only::<Map<String, Value>>(&token, &k).is_ok());
-        std::thread::sleep(Duration::from_secs(2));
-        assert!(verify::<Map<String, Value>>(&token, &k).is_ok());
-        assert!(verify::<Map<String, Value>>(&token, &k1).is_err());
-        std::thread::sleep(Duration::from_secs(2));
-        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
-        assert!(verify_only::<Map<String, Value>>(&token, &k).is_ok());
+    #[test]
+    fn boxed_and_shared_keys() -> Result<()> {
+        let k: Arc<dyn SigningKey + Send + Sync> =
+            Arc::new(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let other: Box<dyn VerificationKey> =
+            Box::new(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        assert!(verify::<Map<String, Value>>(&token, &other).is_err());
+        assert_eq!(other.accepted_algorithms(), ["ES256"]);
+
+        let k = Arc::new(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let vk: Arc<dyn VerificationKey + Send + Sync> = k;
+        verify::<Map<String, Value>>(&token, &vk)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_split_parts() -> Result<()> {
+        #[derive(Serialize, Deserialize)]
+        struct Extra {
+            role: String,
+        }
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::with_claims(Extra {
+            role: "admin".into(),
+        });
+        claims.set_sub("you").set_kid("kid");
+        let token = sign(&mut claims, &k)?;
+
+        let (header, claims, extra) = verify_split::<Extra>(&token, &k, &VerifyOptions::default())?;
+        assert_eq!(header.kid.as_deref(), Some("kid"));
+        assert_eq!(claims.sub(), Some("you"));
+        assert_eq!(extra.role, "admin");
+
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        assert!(verify_split::<Extra>(&token, &k1, &VerifyOptions::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn raw_payload() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let payload = br#"{ "sub":"you",  "a":1 }"#;
+        let token = sign_raw_payload(&mut Header::default(), payload, &k)?;
+        let parts = inspect(&token)?;
+        assert_eq!(
+            URL_SAFE_TRAILING_BITS.decode(parts.payload_b64)?,
+            payload.to_vec()
+        );
+        let verified = verify::<Map<String, Value>>(&token, &k)?;
+        assert_eq!(verified.sub(), Some("you"));
+        assert_eq!(verified.header().alg, "ES256");
+        Ok(())
+    }
+
+    #[test]
+    fn signature_only() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let header = URL_SAFE_TRAILING_BITS.encode(r#"{"alg":"ES256","kid":"k"}"#);
+        let payload = URL_SAFE_TRAILING_BITS.encode("not json");
+        let input = format!("{}.{}", header, payload);
+        let sig = URL_SAFE_TRAILING_BITS.encode(k.sign(input.as_bytes())?);
+        let token = format!("{}.{}", input, sig);
+
+        let options = VerifyOptions::default();
+        let header = verify_signature_only(&token, &k, &options)?;
+        assert_eq!(header.kid.as_deref(), Some("k"));
+        assert!(verify_with_options::<Value>(&token, &k, &options).is_err());
+
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        assert!(matches!(
+            verify_signature_only(&token, &k1, &options),
+            Err(Error::VerificationError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn verify_bytes() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("you"), &k)?;
+        let verified = super::verify_bytes::<Value>(token.as_bytes(), &k)?;
+        assert_eq!(verified.sub(), Some("you"));
+
+        // Temporal claims are only checked by `verify_bytes`.
+        let not_yet = sign(
+            HeaderAndClaims::new_dynamic().set_nbf_from_now(Duration::from_secs(60)),
+            &k,
+        )?;
+        assert!(matches!(
+            super::verify_bytes::<Value>(not_yet.as_bytes(), &k),
+            Err(Error::Before)
+        ));
+        verify_only_bytes::<Value>(not_yet.as_bytes(), &k)?;
+
+        let mut not_utf8 = token.into_bytes();
+        not_utf8.push(0xff);
+        assert!(std::str::from_utf8(&not_utf8).is_err());
+        for result in [
+            super::verify_bytes::<Value>(&not_utf8, &k),
+            verify_only_bytes::<Value>(&not_utf8, &k),
+        ] {
+            assert!(matches!(result, Err(Error::Base64(Segment::Signature, _))));
+        }
+
+        let too_long = vec![b'a'; VerifyOptions::DEFAULT_MAX_TOKEN_LEN + 1];
+        for result in [
+            super::verify_bytes::<Value>(&too_long, &k),
+            verify_only_bytes::<Value>(&too_long, &k),
+        ] {
+            assert!(matches!(result, Err(Error::MalformedToken)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn alg_key_type_mismatch() -> Result<()> {
+        let rsa = crate::rsa::RsaPrivateKey::generate(2048, crate::rsa::RsaAlgorithm::RS256)?;
+        let rsa_any =
+            crate::rsa::RsaPublicKey::from_pem(rsa.public_key_to_pem()?.as_bytes(), None)?;
+        let ec = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let ed = crate::eddsa::Ed25519PrivateKey::generate()?;
+        let hmac = crate::hmac::HmacKey::generate(crate::hmac::HmacAlgorithm::HS256)?;
+
+        // HS256 "signed" with the RSA public key, the classic alg confusion.
+        let hs256 = sign(
+            &mut HeaderAndClaims::new_dynamic(),
+            &crate::hmac::HmacKey::from_bytes(
+                rsa.public_key_to_pem()?.as_bytes(),
+                crate::hmac::HmacAlgorithm::HS256,
+            ),
+        )?;
+        let keys: [&dyn VerificationKey; 4] = [&rsa, &rsa_any, &ec, &ed];
+        for k in keys.iter() {
+            assert!(matches!(
+                verify::<Value>(&hs256, *k),
+                Err(Error::AlgorithmKeyMismatch)
+            ));
+        }
+
+        let es256 = sign(&mut HeaderAndClaims::new_dynamic(), &ec)?;
+        assert!(matches!(
+            verify::<Value>(&es256, &hmac),
+            Err(Error::AlgorithmKeyMismatch)
+        ));
+        // Same key type, different algorithm.
+        let ps256 = crate::rsa::RsaPrivateKey::generate(2048, crate::rsa::RsaAlgorithm::PS256)?;
+        assert!(matches!(
+            verify::<Value>(&sign(&mut HeaderAndClaims::new_dynamic(), &ps256)?, &rsa),
+            Err(Error::VerificationError)
+        ));
+        Ok(())
+    }
 
+    #[test]
+    fn raw_signatures() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let sig = k.sign_base64url(b"webhook body")?;
+        assert!(!sig.contains('='));
+        k.verify_base64url(b"webhook body", &sig, "ES256")?;
+        assert!(matches!(
+            k.verify_base64url(b"webhook body!", &sig, "ES256"),
+            Err(Error::VerificationError)
+        ));
+        assert!(matches!(
+            k.verify_base64url(b"webhook body", "!", "ES256"),
+            Err(Error::Base64(Segment::Signature, _))
+        ));
+
+        let k: Box<dyn SigningKey> = Box::new(k);
+        assert_eq!(k.sign_base64url(b"")?.len(), 86);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_with_multiple_keys() -> Result<()> {
+        let k0 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let k1 = crate::eddsa::Ed25519PrivateKey::generate()?;
+        let k2 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let keys: [&dyn VerificationKey; 2] = [&k0, &k1];
+        let options = VerifyOptions::default();
+
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("you"), &k1)?;
+        let (i, verified) = verify_with_keys::<Map<String, Value>>(&token, &keys, &options)?;
+        assert_eq!(i, 1);
+        assert_eq!(verified.claims().sub.as_deref(), Some("you"));
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k0)?;
+        let (i, _) = verify_with_keys::<Map<String, Value>>(&token, &keys, &options)?;
+        assert_eq!(i, 0);
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k2)?;
+        assert!(verify_with_keys::<Map<String, Value>>(&token, &keys, &options).is_err());
+        assert!(matches!(
+            verify_with_keys::<Map<String, Value>>(&token, &[], &options),
+            Err(Error::NoKey)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_with_selected_key() -> Result<()> {
+        let k0 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let options = VerifyOptions::default();
+        let select = |header: &Header| -> Result<&dyn VerificationKey> {
+            match header.kid.as_deref() {
+                Some("k0") => Ok(&k0),
+                Some("k1") => Ok(&k1),
+                _ => Err(Error::NoKey),
+            }
+        };
+
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_kid("k1").set_sub("you");
+        let token = sign(&mut claims, &k1)?;
+        let verified = verify_with::<Map<String, Value>, _, _>(&token, select, &options)?;
+        assert_eq!(verified.claims().sub.as_deref(), Some("you"));
+
+        claims.set_kid("k0");
+        let token = sign(&mut claims, &k1)?;
+        assert!(verify_with::<Map<String, Value>, _, _>(&token, select, &options).is_err());
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k0)?;
+        assert!(matches!(
+            verify_with::<Map<String, Value>, _, _>(&token, select, &options),
+            Err(Error::NoKey)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn base64_error_segment() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let parts: Vec<&str> = token.split('.').collect();
+
+        for (i, segment) in [Segment::Header, Segment::Payload, Segment::Signature]
+            .iter()
+            .enumerate()
+        {
+            let mut broken = parts.clone();
+            broken[i] = "a*b";
+            let broken = broken.join(".");
+            // The payload is only decoded after the signature is verified.
+            let res = if *segment == Segment::Payload {
+                decode_without_verify::<Map<String, Value>>(&broken)
+            } else {
+                verify_only::<Map<String, Value>>(&broken, &k)
+            };
+            match res {
+                Err(Error::Base64(s, _)) => assert_eq!(s, *segment),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn options_builder() -> Result<()> {
+        assert!(matches!(
+            VerifyOptions::builder().issuer("me").build(),
+            Err(Error::NoAllowedAlgorithms)
+        ));
+        assert!(matches!(
+            VerifyOptions::builder().algorithms(&[]).build(),
+            Err(Error::NoAllowedAlgorithms)
+        ));
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let options = VerifyOptions::builder()
+            .algorithms(&[Algorithm::ES256])
+            .issuer("me")
+            .audience("api")
+            .required_claims(&["scope"])
+            .build()?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_iss("me").add_aud("web").add_aud("api");
+        let token = sign(&mut claims, &k)?;
+        assert!(matches!(
+            verify_with_options::<Value>(&token, &k, &options),
+            Err(Error::MissingClaim("scope"))
+        ));
+        claims.insert("scope", "read");
+        verify_with_options::<Value>(&sign(&mut claims, &k)?, &k, &options)?;
+
+        claims.set_iss("you");
+        assert!(matches!(
+            verify_with_options::<Value>(&sign(&mut claims, &k)?, &k, &options),
+            Err(Error::InvalidIssuer)
+        ));
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_iss("me").add_aud("web").insert("scope", "read");
+        assert!(matches!(
+            verify_with_options::<Value>(&sign(&mut claims, &k)?, &k, &options),
+            Err(Error::InvalidAudience)
+        ));
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?;
+        assert!(matches!(
+            verify_with_options::<Value>(&sign(&mut claims, &k)?, &k, &options),
+            Err(Error::AlgorithmNotAllowed)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn leeway() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.claims_mut().exp = Some(NumericDate::now().saturating_sub(Duration::from_secs(10)));
+        let expired = sign(&mut claims, &k)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_nbf_from_now(Duration::from_secs(10));
+        let early = sign(&mut claims, &k)?;
+
+        let mut options = VerifyOptions::new();
+        assert!(matches!(
+            verify_with_options::<Value>(&expired, &k, &options),
+            Err(Error::Expired)
+        ));
+        assert!(matches!(
+            verify_with_options::<Value>(&early, &k, &options),
+            Err(Error::Before)
+        ));
+        options.set_leeway(Duration::from_secs(60));
+        verify_with_options::<Value>(&expired, &k, &options)?;
+        verify_with_options::<Value>(&early, &k, &options)?;
+        Ok(())
+    }
+
+    #[test]
+    fn require_typ() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token_with = |typ: Option<&str>| {
+            let mut claims = HeaderAndClaims::new_dynamic();
+            claims.header_mut().typ = typ.map(Into::into);
+            sign(&mut claims, &k)
+        };
+        let mut options = VerifyOptions::new();
+        options.set_require_typ(&["JWT", "at+jwt"]);
+
+        for typ in ["JWT", "jwt", "at+jwt", "application/at+JWT"].iter() {
+            verify_with_options::<Value>(&token_with(Some(typ))?, &k, &options)?;
+        }
+        assert!(matches!(
+            verify_with_options::<Value>(&token_with(Some("dpop+jwt"))?, &k, &options),
+            Err(Error::InvalidTyp)
+        ));
+        assert!(matches!(
+            verify_with_options::<Value>(&token_with(None)?, &k, &options),
+            Err(Error::InvalidTyp)
+        ));
+        options.set_allow_missing_typ(true);
+        verify_with_options::<Value>(&token_with(None)?, &k, &options)?;
+        Ok(())
+    }
+
+    #[test]
+    fn pathological_tokens() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = "a.".repeat(100_000);
+        assert!(matches!(
+            verify::<Value>(&token, &k),
+            Err(Error::MalformedToken)
+        ));
+        assert!(matches!(inspect(&token), Err(Error::InvalidToken)));
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        let mut options = VerifyOptions::new();
+        options.set_max_token_len(token.len());
+        verify_with_options::<Value>(&token, &k, &options)?;
+        // Rejected before the oversized payload is decoded.
+        let long = format!("{}.{}.!", "!".repeat(10_000), "!".repeat(10_000));
+        assert!(matches!(
+            verify_with_options::<Value>(&long, &k, &options),
+            Err(Error::MalformedToken)
+        ));
+
+        // Limited by default.
+        let long = format!("{}.{}.!", "!".repeat(8 * 1024), "!".repeat(8 * 1024));
+        assert!(matches!(
+            verify_with_options::<Value>(&long, &k, &VerifyOptions::new()),
+            Err(Error::MalformedToken)
+        ));
+        options.set_max_token_len(usize::MAX);
+        assert!(matches!(
+            verify_with_options::<Value>(&long, &k, &options),
+            Err(Error::Base64(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn case_insensitive_alg() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut token = format!(
+            "{}.{}",
+            URL_SAFE_TRAILING_BITS.encode(r#"{"alg":"es256"}"#),
+            URL_SAFE_TRAILING_BITS.encode(r#"{"sub":"you"}"#),
+        );
+        let sig = k.sign(token.as_bytes())?;
+        token.push('.');
+        token.push_str(&URL_SAFE_TRAILING_BITS.encode(sig));
+
+        let mut options = VerifyOptions::new();
+        options.set_allowed_algorithms(&[Algorithm::ES256]);
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::AlgorithmNotAllowed)
+        ));
+        options.set_case_insensitive_alg(true);
+        let verified = verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        assert_eq!(verified.header().alg, "ES256");
+        assert_eq!(verified.claims().sub.as_deref(), Some("you"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tolerate_sig_padding() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        // ES256 signatures are 64 bytes, i.e. one padding character.
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)? + "=";
+        assert!(matches!(
+            verify::<Map<String, Value>>(&token, &k),
+            Err(Error::Base64(Segment::Signature, _))
+        ));
+
+        let mut options = VerifyOptions::new();
+        options.set_tolerate_sig_padding(true);
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+
+        // Only the signature.
+        let mut parts: Vec<String> = token.split('.').map(Into::into).collect();
+        parts[0].push_str("==");
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&parts.join("."), &k, &options),
+            Err(Error::Base64(Segment::Header, _))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_token() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("you"), &k)?;
+
+        let parts = inspect(&token)?;
+        assert_eq!(parts.header.alg, "ES256");
+        assert_eq!(
+            [parts.header_b64, parts.payload_b64, parts.signature_b64].join("."),
+            token
+        );
+        let sig = URL_SAFE_TRAILING_BITS.decode(parts.signature_b64)?;
+        k.verify(parts.signing_input(), &sig, "ES256")?;
+
+        assert!(matches!(inspect("a.b.c.d.e"), Err(Error::UnexpectedJwe)));
+        assert!(matches!(inspect("a.b.c.d"), Err(Error::InvalidToken)));
+        assert!(matches!(inspect("a..c"), Err(Error::InvalidToken)));
+        assert!(matches!(inspect("a+.b.c"), Err(Error::InvalidToken)));
+        // Header is not JSON.
+        assert!(inspect("YWJj.b.c").is_err());
+        // Header has no `alg`.
+        assert!(inspect("e30.b.c").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_token_age() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.claims_mut().iat = Some(NumericDate::now().saturating_sub(Duration::from_secs(100)));
+        let token = sign(&mut claims, &k)?;
+
+        let mut options = VerifyOptions::new();
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        options.set_max_token_age(Duration::from_secs(200));
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        options.set_max_token_age(Duration::from_secs(60));
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::TokenTooOld)
+        ));
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::MissingClaim("iat"))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_lifetime() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let iat = NumericDate::from_secs(1_000_000_000);
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.claims_mut().iat = Some(iat);
+        claims.claims_mut().exp = iat.checked_add(Duration::from_secs(3600));
+        let token = sign(&mut claims, &k)?;
+
+        let mut options = VerifyOptions::new();
+        options.set_skip_temporal_validation(true);
+        options.set_max_lifetime(Duration::from_secs(3600));
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        options.set_max_lifetime(Duration::from_secs(3599));
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::LifetimeTooLong)
+        ));
+
+        claims.claims_mut().exp = None;
+        let token = sign(&mut claims, &k)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::MissingClaim("exp"))
+        ));
+        claims.claims_mut().exp = iat.checked_add(Duration::from_secs(60));
+        claims.claims_mut().iat = None;
+        let token = sign(&mut claims, &k)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::MissingClaim("iat"))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_temporal_validation() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.claims_mut().exp = Some(NumericDate::from_secs(1_000_000_000));
+        let token = sign(&mut claims, &k)?;
+        assert!(matches!(
+            verify::<Map<String, Value>>(&token, &k),
+            Err(Error::Expired)
+        ));
+
+        let mut options = VerifyOptions::new();
+        options.set_skip_temporal_validation(true);
+        let verified = verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        assert!(verified.is_expired());
+        let exp = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert!(!verified.is_expired_at(exp));
+        assert!(verified.is_expired_at(exp + Duration::from_secs(1)));
+        assert!(verify_with_options::<Map<String, Value>>(&token, &k1, &options).is_err());
+
+        let token = sign(
+            HeaderAndClaims::new_dynamic().set_nbf_from_now(Duration::from_secs(100)),
+            &k,
+        )?;
+        let verified = verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        assert!(!verified.is_expired());
+
+        Ok(())
+    }
+
+    #[test]
+    fn clock_before_epoch() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(
+            HeaderAndClaims::new_dynamic().set_exp_from_now(Duration::from_secs(60)),
+            &k,
+        )?;
+
+        let mut options = VerifyOptions::new();
+        options.set_now(UNIX_EPOCH - Duration::from_secs(1));
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::ClockError)
+        ));
+        options.set_now(SystemTime::now() + Duration::from_secs(120));
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::Expired)
+        ));
+        options.set_now(SystemTime::now());
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn header_repair() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let token = sign(HeaderAndClaims::new_dynamic().insert("typ", "JWT"), &k)?;
+        let verified = verify::<Map<String, Value>>(&token, &k)?;
+        assert_eq!(verified.header().typ, None);
+
+        let mut options = VerifyOptions::new();
+        options.set_header_repair(|header, payload| {
+            if header.typ.is_none() {
+                header.typ = payload["typ"].as_str().map(Into::into);
+            }
+        });
+        let verified = verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        assert_eq!(verified.header().typ.as_deref(), Some("JWT"));
+        assert_eq!(verified.claims().extra["typ"], "JWT");
+
+        let k1 = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        assert!(verify_with_options::<Map<String, Value>>(&token, &k1, &options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fips_reasons() {
+        assert!(is_fips_rejection("digest not allowed"));
+        assert!(is_fips_rejection("Key size too small"));
+        assert!(is_fips_rejection("fips module entering error state"));
+        assert!(!is_fips_rejection("bad signature"));
+
+        let e = Error::from(ErrorStack::get());
+        assert!(matches!(e, Error::OpenSsl(_)));
+
+        let too_small = || openssl::rsa::Rsa::generate(256).unwrap_err();
+        if too_small()
+            .errors()
+            .iter()
+            .any(|e| e.reason().is_some_and(is_fips_rejection))
+        {
+            assert!(matches!(
+                classify_error_stack(too_small(), false),
+                Error::OpenSsl(_)
+            ));
+            assert!(matches!(
+                classify_error_stack(too_small(), true),
+                Error::FipsNotAllowed(_)
+            ));
+        }
+        if !fips_enabled() {
+            assert!(matches!(Error::from(too_small()), Error::OpenSsl(_)));
+        }
+    }
+
+    #[test]
+    fn numeric_dates() -> Result<()> {
+        for (json, secs) in [
+            (r#"{"exp":1700000000}"#, 1700000000),
+            (r#"{"exp":1700000000.0}"#, 1700000000),
+            (r#"{"exp":1700000000.5}"#, 1700000000),
+            (r#"{"exp":1700000000.999}"#, 1700000000),
+            (r#"{"exp":1.7E9}"#, 1700000000),
+        ]
+        .iter()
+        {
+            let claims: Claims<()> = serde_json::from_str(json)?;
+            assert_eq!(claims.exp(), Some(*secs), "{}", json);
+        }
+        assert!(serde_json::from_str::<Claims<()>>(r#"{"nbf":-1}"#).is_err());
+        assert!(serde_json::from_str::<Claims<()>>(r#"{"iat":"1700000000"}"#).is_err());
+
+        // The fraction of `exp` does not extend validity.
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let json = format!(r#"{{"exp":{}.9}}"#, now.as_secs().saturating_sub(1));
+        let claims: Claims<()> = serde_json::from_str(&json)?;
+        let mut options = VerifyOptions::new();
+        options.set_now(UNIX_EPOCH + Duration::from_secs(now.as_secs()));
+        assert!(matches!(options.check(&claims), Err(Error::Expired)));
         Ok(())
     }
 
@@ -542,7 +3339,7 @@ mod tests {
         let r = base64::read::DecoderReader::new(&mut json, &URL_SAFE_TRAILING_BITS);
 
         let claims: Claims<Value> = serde_json::from_reader(r).unwrap();
-        assert_eq!(claims.iat, Some(Duration::from_secs(1_692_190_125)));
-        assert_eq!(claims.exp, Some(Duration::from_secs(1_692_193_725)));
+        assert_eq!(claims.iat, Some(NumericDate::from_secs(1_692_190_125)));
+        assert_eq!(claims.exp, Some(NumericDate::from_secs(1_692_193_725)));
     }
 }
@@ -0,0 +1,161 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// A JWT NumericDate, i.e. whole seconds since the unix epoch. Used for `exp`,
+/// `nbf` and `iat`.
+///
+/// Serializes as an integer. Both integers and fractional numbers are accepted
+/// when deserializing. Fractions are truncated, i.e. rounded toward the past,
+/// so e.g. `exp: 1700000000.5` expires at `1700000000`.
+///
+/// Conversions from times before the epoch clamp to the epoch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NumericDate(u64);
+
+impl NumericDate {
+    #[inline]
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    /// The current system time.
+    #[inline]
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    #[inline]
+    pub const fn as_secs(self) -> u64 {
+        self.0
+    }
+
+    /// Time since the unix epoch.
+    #[inline]
+    pub const fn to_duration(self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+
+    /// `None` if the time is too far in the future to be represented as a
+    /// `SystemTime` on this platform.
+    #[inline]
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        UNIX_EPOCH.checked_add(self.to_duration())
+    }
+
+    /// `None` on overflow.
+    #[inline]
+    pub fn checked_add(self, d: Duration) -> Option<Self> {
+        self.0.checked_add(d.as_secs()).map(Self)
+    }
+
+    /// Saturates at the epoch.
+    #[inline]
+    pub fn saturating_sub(self, d: Duration) -> Self {
+        Self(self.0.saturating_sub(d.as_secs()))
+    }
+}
+
+impl From<Duration> for NumericDate {
+    /// From time since the unix epoch, truncating sub-second precision.
+    #[inline]
+    fn from(d: Duration) -> Self {
+        Self(d.as_secs())
+    }
+}
+
+impl From<SystemTime> for NumericDate {
+    #[inline]
+    fn from(t: SystemTime) -> Self {
+        t.duration_since(UNIX_EPOCH).unwrap_or_default().into()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for NumericDate {
+    #[inline]
+    fn from(t: chrono::DateTime<chrono::Utc>) -> Self {
+        Self(t.timestamp().max(0) as u64)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl NumericDate {
+    /// `None` if the time is out of the range of `chrono::DateTime`.
+    #[inline]
+    pub fn to_chrono(self) -> Option<chrono::DateTime<chrono::Utc>> {
+        use std::convert::TryFrom;
+
+        let secs = i64::try_from(self.0).ok()?;
+        chrono::DateTime::from_timestamp(secs, 0)
+    }
+}
+
+impl Serialize for NumericDate {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for NumericDate {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        if !(secs >= 0.0 && secs < u64::MAX as f64) {
+            return Err(serde::de::Error::custom("invalid NumericDate"));
+        }
+        Ok(Self(secs.trunc() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_700_000_000_900);
+        let d = NumericDate::from(t);
+        assert_eq!(d.as_secs(), 1_700_000_000);
+        assert_eq!(
+            d.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            NumericDate::from(UNIX_EPOCH - Duration::from_secs(1)),
+            NumericDate::from_secs(0)
+        );
+        assert_eq!(NumericDate::from_secs(u64::MAX).to_system_time(), None);
+
+        assert_eq!(
+            d.checked_add(Duration::from_secs(60)),
+            Some(NumericDate::from_secs(1_700_000_060))
+        );
+        assert_eq!(
+            NumericDate::from_secs(u64::MAX).checked_add(Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(d.saturating_sub(Duration::from_secs(u64::MAX)).as_secs(), 0);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        let t = chrono::DateTime::from_timestamp(1_700_000_000, 5).unwrap();
+        let d = NumericDate::from(t);
+        assert_eq!(d.as_secs(), 1_700_000_000);
+        assert_eq!(
+            d.to_chrono(),
+            chrono::DateTime::from_timestamp(1_700_000_000, 0)
+        );
+        assert_eq!(
+            NumericDate::from(chrono::DateTime::from_timestamp(-1, 0).unwrap()).as_secs(),
+            0
+        );
+        assert_eq!(NumericDate::from_secs(u64::MAX).to_chrono(), None);
+    }
+}
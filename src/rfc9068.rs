@@ -0,0 +1,103 @@
+//! JWT profile for OAuth 2.0 access tokens (RFC 9068).
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    sign, verify_with_options, Error, HeaderAndClaims, Result, SigningKey, VerificationKey,
+    VerifyOptions,
+};
+
+/// Claims that RFC 9068 access tokens must have.
+pub const RFC9068_REQUIRED_CLAIMS: &[&str] =
+    &["iss", "exp", "aud", "sub", "client_id", "iat", "jti"];
+
+/// Sign an RFC 9068 access token: like [`sign`], but `typ` is set to
+/// `at+jwt`, and signing fails with [`Error::MissingClaim`] unless all of
+/// [`RFC9068_REQUIRED_CLAIMS`] are set.
+pub fn sign_rfc9068<ExtraClaims: Serialize>(
+    claims: &mut HeaderAndClaims<ExtraClaims>,
+    k: &dyn SigningKey,
+) -> Result<String> {
+    let payload = serde_json::to_value(&claims.claims)?;
+    if let Some(missing) = RFC9068_REQUIRED_CLAIMS
+        .iter()
+        .find(|name| matches!(payload.get(**name), None | Some(Value::Null)))
+    {
+        return Err(Error::MissingClaim(missing));
+    }
+    claims.header.typ = Some("at+jwt".into());
+    sign(claims, k)
+}
+
+/// Verify an RFC 9068 access token: like [`verify_with_options`], but `typ`
+/// must be `at+jwt` (or `application/at+jwt`), and all of
+/// [`RFC9068_REQUIRED_CLAIMS`] are required.
+///
+/// The spec also requires checking `iss` and `aud`, so set them in `options`.
+pub fn verify_rfc9068<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &VerifyOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let mut options = options.clone();
+    options
+        .set_require_typ(&["at+jwt"])
+        .set_allow_missing_typ(false)
+        .required_claims
+        .extend_from_slice(RFC9068_REQUIRED_CLAIMS);
+    verify_with_options(token, k, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Map;
+
+    use super::*;
+    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+
+    fn access_token() -> HeaderAndClaims<Map<String, Value>> {
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims
+            .set_iss("https://as.example.com")
+            .set_sub("you")
+            .add_aud("https://rs.example.com")
+            .set_exp_from_now(Duration::from_secs(60))
+            .set_iat_now()
+            .set_jti("1")
+            .insert("client_id", "app");
+        claims
+    }
+
+    #[test]
+    fn rfc9068() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut options = VerifyOptions::new();
+        options.set_audience("https://rs.example.com");
+
+        let token = sign_rfc9068(&mut access_token(), &k)?;
+        let verified = verify_rfc9068::<Map<String, Value>>(&token, &k, &options)?;
+        assert_eq!(verified.header().typ.as_deref(), Some("at+jwt"));
+
+        let mut claims = access_token();
+        claims.claims.extra.remove("client_id");
+        assert!(matches!(
+            sign_rfc9068(&mut claims, &k),
+            Err(Error::MissingClaim("client_id"))
+        ));
+        // Plain JWTs with the same claims are not access tokens.
+        let token = sign(&mut access_token(), &k)?;
+        assert!(matches!(
+            verify_rfc9068::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::InvalidTyp)
+        ));
+        let token = sign(claims.set_typ("at+jwt"), &k)?;
+        assert!(matches!(
+            verify_rfc9068::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::MissingClaim("client_id"))
+        ));
+        Ok(())
+    }
+}
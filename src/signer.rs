@@ -0,0 +1,154 @@
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use smallvec::SmallVec;
+
+use crate::{sign, Algorithm, HeaderAndClaims, Result, SigningKey};
+
+/// A long-lived signer whose key can be rotated.
+///
+/// Each [`sign`](Self::sign) uses the key that is current when it is called.
+/// Wrap keys in [`WithKid`](crate::jwk::WithKid) so that the `kid` header
+/// follows the rotation.
+pub struct Signer {
+    key: RwLock<Arc<dyn SigningKey + Send + Sync>>,
+}
+
+impl Signer {
+    pub fn new(key: Arc<dyn SigningKey + Send + Sync>) -> Self {
+        Self {
+            key: RwLock::new(key),
+        }
+    }
+
+    /// The current key.
+    pub fn current(&self) -> Arc<dyn SigningKey + Send + Sync> {
+        self.key.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the key. Signing operations that already started keep using
+    /// the old key.
+    pub fn rotate(&self, key: Arc<dyn SigningKey + Send + Sync>) {
+        *self.key.write().unwrap_or_else(|e| e.into_inner()) = key;
+    }
+
+    /// Sign with the current key. See [`sign`](crate::sign).
+    ///
+    /// Unlike `sign`, the `kid` header is removed if the current key has no
+    /// `kid`, so that a reused `claims` doesn't point at a previous key.
+    pub fn sign<ExtraClaims: Serialize>(
+        &self,
+        claims: &mut HeaderAndClaims<ExtraClaims>,
+    ) -> Result<String> {
+        let key = self.current();
+        if key.kid().is_none() {
+            claims.header.kid = None;
+        }
+        sign(claims, &key)
+    }
+}
+
+/// See [`RemoteSigner::new`].
+pub type SignFn = Box<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync>;
+
+/// A [`SigningKey`] whose signatures are made by a function, e.g. a call to
+/// a KMS or HSM, or to a local key standing in for one in tests.
+pub struct RemoteSigner {
+    alg: Algorithm,
+    kid: Option<String>,
+    sign_fn: SignFn,
+}
+
+impl RemoteSigner {
+    /// `sign_fn` is called with the signing input and must return the
+    /// signature in the JWS format of `alg`. E.g. ECDSA signatures must be
+    /// `r || s`, not DER as returned by many KMSs.
+    pub fn new(alg: Algorithm, kid: Option<String>, sign_fn: SignFn) -> Self {
+        Self { alg, kid, sign_fn }
+    }
+}
+
+impl SigningKey for RemoteSigner {
+    fn alg(&self) -> &'static str {
+        self.alg.as_str()
+    }
+
+    fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+        (self.sign_fn)(v).map(SmallVec::from_vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        eddsa::Ed25519PrivateKey,
+        jwk::WithKid,
+        verify,
+    };
+
+    use super::*;
+
+    #[test]
+    fn rotate() -> Result<()> {
+        let k0 = Arc::new(WithKid::new(
+            "0".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        ));
+        let k1 = Arc::new(WithKid::new("1".into(), Ed25519PrivateKey::generate()?));
+        let signer = Signer::new(k0.clone());
+
+        let mut claims = HeaderAndClaims::new_dynamic();
+        let token = signer.sign(&mut claims)?;
+        let verified = verify::<Map<String, Value>>(&token, &k0)?;
+        assert_eq!(verified.header().kid.as_deref(), Some("0"));
+
+        signer.rotate(k1.clone());
+        let token = signer.sign(&mut claims)?;
+        assert!(verify::<Map<String, Value>>(&token, &k0).is_err());
+        let verified = verify::<Map<String, Value>>(&token, &k1)?;
+        assert_eq!(verified.header().kid.as_deref(), Some("1"));
+        assert_eq!(verified.header().alg, "EdDSA");
+        assert_eq!(signer.current().alg(), "EdDSA");
+
+        // Rotating to a key without a `kid` doesn't keep the old one.
+        let k2 = Arc::new(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        signer.rotate(k2.clone());
+        let token = signer.sign(&mut claims)?;
+        let verified = verify::<Map<String, Value>>(&token, &k2)?;
+        assert_eq!(verified.header().kid, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remote_signer() -> Result<()> {
+        let hsm = Arc::new(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        let signer = RemoteSigner::new(Algorithm::ES256, Some("hsm".into()), {
+            let hsm = hsm.clone();
+            Box::new(move |v| Ok(hsm.sign(v)?.to_vec()))
+        });
+
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &signer)?;
+        let verified = verify::<Map<String, Value>>(&token, &hsm)?;
+        assert_eq!(verified.header().kid.as_deref(), Some("hsm"));
+        assert_eq!(verified.header().alg, "ES256");
+
+        let failing = RemoteSigner::new(
+            Algorithm::ES256,
+            None,
+            Box::new(|_| Err(crate::Error::UnsupportedOrInvalidKey)),
+        );
+        assert!(matches!(
+            sign(&mut HeaderAndClaims::new_dynamic(), &failing),
+            Err(crate::Error::UnsupportedOrInvalidKey)
+        ));
+        Ok(())
+    }
+}
@@ -1,13 +1,19 @@
 //! Enum of HMAC / EC / RSA / Ed Keys.
 
-use openssl::pkey::{Id, PKey};
+use openssl::{
+    asn1::Asn1Time,
+    pkey::{Id, PKey, Public},
+    x509::X509,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::{
-    ecdsa::{EcdsaPrivateKey, EcdsaPublicKey},
+    ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey, EcdsaPublicKey},
     eddsa::{Ed25519PrivateKey, Ed25519PublicKey},
     jwk::Jwk,
     rsa::{RsaAlgorithm, RsaPrivateKey, RsaPublicKey},
-    Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey, VerificationKey,
+    Error, KeyPolicy, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey, VerificationKey,
 };
 
 /// An RSA, EC or Ed25519 private key.
@@ -15,7 +21,7 @@ use crate::{
 /// Use this if you just want to load SOME private key from an external pem
 /// file.
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SomePrivateKey {
     Ed25519(Ed25519PrivateKey),
     Ecdsa(EcdsaPrivateKey),
@@ -27,13 +33,83 @@ pub enum SomePrivateKey {
 /// Use this if you just want to load SOME public key from an external pem file
 /// or JWK.
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SomePublicKey {
     Ed25519(Ed25519PublicKey),
     Ecdsa(EcdsaPublicKey),
     Rsa(RsaPublicKey),
 }
 
+/// PEM encoding of a public key.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PemFormat {
+    /// `PUBLIC KEY`, i.e. SubjectPublicKeyInfo. Supported by all key types.
+    Spki,
+    /// `RSA PUBLIC KEY`. Only for RSA keys.
+    Pkcs1,
+    /// SEC1 defines no PEM encoding for public keys, so this is rejected for
+    /// all key types. Use [`Spki`](Self::Spki) for EC keys.
+    Sec1,
+}
+
+/// Metadata of a key, e.g. for audit logs. See [`SomePrivateKey::describe`]
+/// and [`SomePublicKey::describe`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct KeyInfo {
+    /// JWK key type, e.g. `EC`.
+    pub kty: &'static str,
+    /// The `alg` this key is used with, e.g. `ES256`. `None` for RSA public
+    /// keys that are not restricted to one algorithm.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<&'static str>,
+    /// Key size in bits. For EC and OKP keys, the size of the curve.
+    pub bits: u32,
+    /// JWK curve name of EC and OKP keys, e.g. `P-256`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<&'static str>,
+    /// Public exponent of RSA keys, if it fits in 64 bits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<u64>,
+}
+
+impl KeyInfo {
+    fn ed25519() -> Self {
+        Self {
+            kty: "OKP",
+            alg: Some("EdDSA"),
+            bits: 256,
+            crv: Some("Ed25519"),
+            e: None,
+        }
+    }
+
+    fn ecdsa(alg: EcdsaAlgorithm) -> Self {
+        Self {
+            kty: "EC",
+            alg: Some(alg.name()),
+            bits: alg.bits(),
+            crv: Some(alg.curve_name()),
+            e: None,
+        }
+    }
+
+    fn rsa(alg: Option<&'static str>, bits: u32, e: Result<Vec<u8>>) -> Self {
+        let e = e
+            .ok()
+            .filter(|e| e.len() <= 8)
+            .map(|e| e.iter().fold(0, |acc, b| acc << 8 | u64::from(*b)));
+        Self {
+            kty: "RSA",
+            alg,
+            bits,
+            crv: None,
+            e,
+        }
+    }
+}
+
 impl From<Ed25519PrivateKey> for SomePrivateKey {
     #[inline]
     fn from(k: Ed25519PrivateKey) -> SomePrivateKey {
@@ -84,11 +160,19 @@ impl SomePrivateKey {
     ///
     /// For an RSA private key, `if_rsa_algorithm` is used.
     pub fn from_pem(pem: &[u8], if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
+        Self::from_pem_with_policy(pem, if_rsa_algorithm, &KeyPolicy::default())
+    }
+
+    pub fn from_pem_with_policy(
+        pem: &[u8],
+        if_rsa_algorithm: RsaAlgorithm,
+        policy: &KeyPolicy,
+    ) -> Result<Self> {
         let pk = PKey::private_key_from_pem(pem)?;
 
         match pk.id() {
             Id::RSA => {
-                let k = RsaPrivateKey::from_pkey(pk, if_rsa_algorithm)?;
+                let k = RsaPrivateKey::from_pkey(pk, if_rsa_algorithm, policy)?;
                 Ok(Self::Rsa(k))
             }
             Id::EC => {
@@ -118,6 +202,57 @@ impl SomePrivateKey {
             SomePrivateKey::Rsa(rsa) => rsa.public_key_to_pem(),
         }
     }
+
+    /// Encode the public key as PEM in `format`.
+    ///
+    /// Returns `Error::UnsupportedOrInvalidKey` if `format` is not supported
+    /// for this key type, e.g. PKCS#1 for an EC key or SEC1 for an RSA key.
+    pub fn public_key_to_pem_as(&self, format: PemFormat) -> Result<String> {
+        match (self, format) {
+            (_, PemFormat::Spki) => self.public_key_to_pem(),
+            (SomePrivateKey::Rsa(rsa), PemFormat::Pkcs1) => rsa.public_key_to_pem_pkcs1(),
+            _ => Err(Error::UnsupportedOrInvalidKey),
+        }
+    }
+
+    #[inline]
+    pub fn as_ed25519(&self) -> Option<&Ed25519PrivateKey> {
+        match self {
+            SomePrivateKey::Ed25519(ed) => Some(ed),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_ecdsa(&self) -> Option<&EcdsaPrivateKey> {
+        match self {
+            SomePrivateKey::Ecdsa(ec) => Some(ec),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_rsa(&self) -> Option<&RsaPrivateKey> {
+        match self {
+            SomePrivateKey::Rsa(rsa) => Some(rsa),
+            _ => None,
+        }
+    }
+
+    /// The `alg` name this key signs with, e.g. `ES256`.
+    #[inline]
+    pub fn algorithm_name(&self) -> &'static str {
+        self.alg()
+    }
+
+    /// Type, algorithm and size of this key.
+    pub fn describe(&self) -> KeyInfo {
+        match self {
+            SomePrivateKey::Ed25519(_) => KeyInfo::ed25519(),
+            SomePrivateKey::Ecdsa(ec) => KeyInfo::ecdsa(ec.algorithm()),
+            SomePrivateKey::Rsa(rsa) => KeyInfo::rsa(Some(rsa.alg()), rsa.bits(), rsa.e()),
+        }
+    }
 }
 
 impl PublicKeyToJwk for SomePrivateKey {
@@ -149,10 +284,67 @@ impl SomePublicKey {
     /// For an RSA public key, signatures generated by any RSA algorithms can be
     /// verified.
     pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        Self::from_pem_with_policy(pem, &KeyPolicy::default())
+    }
+
+    pub fn from_pem_with_policy(pem: &[u8], policy: &KeyPolicy) -> Result<Self> {
         let pk = PKey::public_key_from_pem(pem)?;
+        Self::from_pkey(pk, policy)
+    }
+
+    /// The public key of a PEM X.509 certificate, like
+    /// [`from_pem`](Self::from_pem).
+    ///
+    /// Fails with [`Error::CertificateExpired`] if the certificate is not
+    /// valid now. The certificate chain is not verified.
+    pub fn from_x509_pem(pem: &[u8]) -> Result<Self> {
+        Self::from_x509_pem_with(pem, &KeyPolicy::default(), Some(SystemTime::now()))
+    }
+
+    /// Like [`from_x509_pem`](Self::from_x509_pem), for a DER certificate.
+    pub fn from_x509_der(der: &[u8]) -> Result<Self> {
+        Self::from_x509_der_with(der, &KeyPolicy::default(), Some(SystemTime::now()))
+    }
+
+    /// Like [`from_x509_pem`](Self::from_x509_pem), but the key must be
+    /// allowed by `policy`, and the certificate must be valid at `now`
+    /// instead of the current time. With `None`, its validity period isn't
+    /// checked at all.
+    pub fn from_x509_pem_with(
+        pem: &[u8],
+        policy: &KeyPolicy,
+        now: Option<SystemTime>,
+    ) -> Result<Self> {
+        Self::from_x509(X509::from_pem(pem)?, policy, now)
+    }
+
+    /// Like [`from_x509_pem_with`](Self::from_x509_pem_with), for a DER
+    /// certificate.
+    pub fn from_x509_der_with(
+        der: &[u8],
+        policy: &KeyPolicy,
+        now: Option<SystemTime>,
+    ) -> Result<Self> {
+        Self::from_x509(X509::from_der(der)?, policy, now)
+    }
+
+    fn from_x509(cert: X509, policy: &KeyPolicy, now: Option<SystemTime>) -> Result<Self> {
+        if let Some(now) = now {
+            let now = now
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| Error::ClockError)?;
+            let now = Asn1Time::from_unix(now.as_secs() as i64)?;
+            if cert.not_before() > now || cert.not_after() < now {
+                return Err(Error::CertificateExpired);
+            }
+        }
+        Self::from_pkey(cert.public_key()?, policy)
+    }
+
+    fn from_pkey(pk: PKey<Public>, policy: &KeyPolicy) -> Result<Self> {
         match pk.id() {
             Id::RSA => {
-                let k = RsaPublicKey::from_pkey(pk, None)?;
+                let k = RsaPublicKey::from_pkey(pk, None, policy)?;
                 Ok(Self::Rsa(k))
             }
             Id::EC => {
@@ -167,6 +359,24 @@ impl SomePublicKey {
         }
     }
 
+    /// Parse each PEM block of a concatenated bundle, e.g. a trust store of
+    /// several `BEGIN PUBLIC KEY` blocks, in order.
+    ///
+    /// Blocks that are not supported public keys, e.g. certificates, fail the
+    /// whole bundle, unless `skip_invalid` is set, in which case they are
+    /// left out. An unterminated block is always an error.
+    pub fn from_pem_bundle(pem: &[u8], skip_invalid: bool) -> Result<Vec<Self>> {
+        let mut keys = Vec::new();
+        for block in pem_blocks(pem)? {
+            match Self::from_pem(block) {
+                Ok(k) => keys.push(k),
+                Err(_) if skip_invalid => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(keys)
+    }
+
     pub fn to_pem(&self) -> Result<String> {
         match self {
             SomePublicKey::Ed25519(ed) => ed.to_pem(),
@@ -174,6 +384,51 @@ impl SomePublicKey {
             SomePublicKey::Rsa(rsa) => rsa.to_pem(),
         }
     }
+
+    #[inline]
+    pub fn as_ed25519(&self) -> Option<&Ed25519PublicKey> {
+        match self {
+            SomePublicKey::Ed25519(ed) => Some(ed),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_ecdsa(&self) -> Option<&EcdsaPublicKey> {
+        match self {
+            SomePublicKey::Ecdsa(ec) => Some(ec),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_rsa(&self) -> Option<&RsaPublicKey> {
+        match self {
+            SomePublicKey::Rsa(rsa) => Some(rsa),
+            _ => None,
+        }
+    }
+
+    /// The `alg` name this key verifies, e.g. `ES256`. `RSA` for RSA keys that
+    /// are not restricted to one algorithm.
+    pub fn algorithm_name(&self) -> &'static str {
+        match self {
+            SomePublicKey::Ed25519(_) => "EdDSA",
+            SomePublicKey::Ecdsa(ec) => ec.algorithm().name(),
+            SomePublicKey::Rsa(rsa) => rsa.algorithm.map_or("RSA", |alg| alg.name()),
+        }
+    }
+
+    /// Type, algorithm and size of this key.
+    pub fn describe(&self) -> KeyInfo {
+        match self {
+            SomePublicKey::Ed25519(_) => KeyInfo::ed25519(),
+            SomePublicKey::Ecdsa(ec) => KeyInfo::ecdsa(ec.algorithm()),
+            SomePublicKey::Rsa(rsa) => {
+                KeyInfo::rsa(rsa.algorithm.map(|alg| alg.name()), rsa.bits(), rsa.e())
+            }
+        }
+    }
 }
 
 impl SigningKey for SomePrivateKey {
@@ -202,6 +457,31 @@ impl VerificationKey for SomePrivateKey {
             SomePrivateKey::Rsa(rsa) => rsa.verify(v, sig, alg),
         }
     }
+
+    fn verify_reader(
+        &self,
+        input: &mut dyn std::io::Read,
+        sig: &[u8],
+        alg: &str,
+    ) -> crate::Result<()> {
+        match self {
+            SomePrivateKey::Ed25519(ed) => ed.verify_reader(input, sig, alg),
+            SomePrivateKey::Ecdsa(ec) => ec.verify_reader(input, sig, alg),
+            SomePrivateKey::Rsa(rsa) => rsa.verify_reader(input, sig, alg),
+        }
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        match self {
+            SomePrivateKey::Ed25519(ed) => ed.accepted_algorithms(),
+            SomePrivateKey::Ecdsa(ec) => ec.accepted_algorithms(),
+            SomePrivateKey::Rsa(rsa) => rsa.accepted_algorithms(),
+        }
+    }
+
+    fn can_sign(&self) -> bool {
+        true
+    }
 }
 
 impl VerificationKey for SomePublicKey {
@@ -212,6 +492,27 @@ impl VerificationKey for SomePublicKey {
             SomePublicKey::Rsa(rsa) => rsa.verify(v, sig, alg),
         }
     }
+
+    fn verify_reader(
+        &self,
+        input: &mut dyn std::io::Read,
+        sig: &[u8],
+        alg: &str,
+    ) -> crate::Result<()> {
+        match self {
+            SomePublicKey::Ed25519(ed) => ed.verify_reader(input, sig, alg),
+            SomePublicKey::Ecdsa(ec) => ec.verify_reader(input, sig, alg),
+            SomePublicKey::Rsa(rsa) => rsa.verify_reader(input, sig, alg),
+        }
+    }
+
+    fn accepted_algorithms(&self) -> Vec<&'static str> {
+        match self {
+            SomePublicKey::Ed25519(ed) => ed.accepted_algorithms(),
+            SomePublicKey::Ecdsa(ec) => ec.accepted_algorithms(),
+            SomePublicKey::Rsa(rsa) => rsa.accepted_algorithms(),
+        }
+    }
 }
 
 impl PublicKeyToJwk for SomePublicKey {
@@ -223,3 +524,323 @@ impl PublicKeyToJwk for SomePublicKey {
         }
     }
 }
+
+/// Serializes as a private JWK.
+impl Serialize for SomePrivateKey {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.private_key_to_jwk()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Deserializes from a private JWK. RSA keys without `alg` are `RS256`.
+impl<'de> Deserialize<'de> for SomePrivateKey {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Jwk::deserialize(deserializer)?
+            .to_signing_key(RsaAlgorithm::RS256)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serializes as a public JWK.
+impl Serialize for SomePublicKey {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.public_key_to_jwk()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Deserializes from a public JWK, see [`Jwk::to_verification_key`].
+impl<'de> Deserialize<'de> for SomePublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Jwk::deserialize(deserializer)?
+            .to_verification_key()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// Split `pem` into `-----BEGIN ...-----` to `-----END ...-----` blocks.
+// Anything between blocks is ignored, like OpenSSL does.
+fn pem_blocks(mut pem: &[u8]) -> Result<Vec<&[u8]>> {
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    let mut blocks = Vec::new();
+    while let Some(start) = find(pem, b"-----BEGIN ") {
+        let rest = &pem[start..];
+        let label = find(rest, b"-----END ").ok_or(Error::UnsupportedOrInvalidKey)? + 9;
+        let len = label + find(&rest[label..], b"-----").ok_or(Error::UnsupportedOrInvalidKey)? + 5;
+        blocks.push(&rest[..len]);
+        pem = &rest[len..];
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x509() -> Result<()> {
+        use openssl::{
+            ec::{EcGroup, EcKey},
+            hash::MessageDigest,
+            nid::Nid,
+            x509::X509Builder,
+        };
+
+        let pk = PKey::from_ec_key(EcKey::generate(
+            EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?.as_ref(),
+        )?)?;
+        let cert = |not_before: &Asn1Time, not_after: &Asn1Time| -> Result<X509> {
+            let mut builder = X509Builder::new()?;
+            builder.set_pubkey(&pk)?;
+            builder.set_not_before(not_before)?;
+            builder.set_not_after(not_after)?;
+            builder.sign(&pk, MessageDigest::sha256())?;
+            Ok(builder.build())
+        };
+
+        let valid = cert(&Asn1Time::days_from_now(0)?, &Asn1Time::days_from_now(1)?)?;
+        let k = SomePublicKey::from_x509_pem(&valid.to_pem()?)?;
+        assert!(k.as_ecdsa().is_some());
+        assert_eq!(k.accepted_algorithms(), ["ES256"]);
+        assert_eq!(k.to_pem()?.as_bytes(), pk.public_key_to_pem()?);
+        SomePublicKey::from_x509_der(&valid.to_der()?)?;
+
+        let expired = cert(&Asn1Time::from_unix(0)?, &Asn1Time::from_unix(1)?)?;
+        assert!(matches!(
+            SomePublicKey::from_x509_pem(&expired.to_pem()?),
+            Err(Error::CertificateExpired)
+        ));
+        let not_yet_valid = cert(&Asn1Time::days_from_now(1)?, &Asn1Time::days_from_now(2)?)?;
+        assert!(matches!(
+            SomePublicKey::from_x509_der(&not_yet_valid.to_der()?),
+            Err(Error::CertificateExpired)
+        ));
+
+        let policy = KeyPolicy::default();
+        SomePublicKey::from_x509_pem_with(&expired.to_pem()?, &policy, None)?;
+        SomePublicKey::from_x509_pem_with(&expired.to_pem()?, &policy, Some(UNIX_EPOCH))?;
+        assert!(matches!(
+            SomePublicKey::from_x509_der_with(
+                &expired.to_der()?,
+                &policy,
+                Some(UNIX_EPOCH + std::time::Duration::from_secs(2))
+            ),
+            Err(Error::CertificateExpired)
+        ));
+        SomePublicKey::from_x509_der_with(&not_yet_valid.to_der()?, &policy, None)?;
+        assert!(SomePublicKey::from_x509_pem(&pk.public_key_to_pem()?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn downcast() -> Result<()> {
+        let k = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?);
+        assert!(k.as_ecdsa().is_some());
+        assert!(k.as_rsa().is_none());
+        assert!(k.as_ed25519().is_none());
+        assert_eq!(k.algorithm_name(), "ES384");
+
+        let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        assert!(pk.as_ecdsa().is_some());
+        assert!(pk.as_rsa().is_none());
+        assert_eq!(pk.algorithm_name(), "ES384");
+
+        let k = SomePrivateKey::from(RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?);
+        assert_eq!(k.algorithm_name(), "PS256");
+        let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        assert!(pk.as_rsa().is_some());
+        assert_eq!(pk.algorithm_name(), "RSA");
+
+        let k = SomePrivateKey::from(Ed25519PrivateKey::generate()?);
+        assert!(k.as_ed25519().is_some());
+        assert_eq!(k.algorithm_name(), "EdDSA");
+        Ok(())
+    }
+
+    #[test]
+    fn serde_as_jwk() -> Result<()> {
+        #[derive(Serialize, Deserialize)]
+        struct Config {
+            signing: SomePrivateKey,
+            trusted: Vec<SomePublicKey>,
+        }
+
+        let k = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?);
+        let other = Ed25519PrivateKey::generate()?;
+        let config = Config {
+            trusted: vec![
+                SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?,
+                SomePublicKey::from_pem(other.public_key_to_pem()?.as_bytes())?,
+            ],
+            signing: k,
+        };
+        let json = serde_json::to_value(&config)?;
+        assert_eq!(json["signing"]["kty"], "EC");
+        assert!(json["signing"]["d"].is_string());
+        assert!(json["trusted"][0].get("d").is_none());
+
+        let config: Config = serde_json::from_value(json)?;
+        assert_eq!(config.signing.algorithm_name(), "ES384");
+        let sig = config.signing.sign(b"...")?;
+        config.trusted[0].verify(b"...", &sig, "ES384")?;
+        assert!(config.trusted[1].as_ed25519().is_some());
+
+        let rsa = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        let mut jwk = serde_json::to_value(SomePrivateKey::from(rsa))?;
+        assert_eq!(
+            serde_json::from_value::<SomePrivateKey>(jwk.clone())?.algorithm_name(),
+            "PS256"
+        );
+        jwk.as_object_mut().unwrap().remove("alg");
+        assert_eq!(
+            serde_json::from_value::<SomePrivateKey>(jwk)?.algorithm_name(),
+            "RS256"
+        );
+        assert!(serde_json::from_str::<SomePublicKey>(r#"{"kty":"oct","k":"AA"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pem_bundle() -> Result<()> {
+        let ec = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let ed = Ed25519PrivateKey::generate()?;
+        let rsa = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let bundle = format!(
+            "# trust store\n{}{}\n{}",
+            ec.public_key_to_pem()?,
+            ed.public_key_to_pem()?,
+            rsa.public_key_to_pem()?,
+        );
+        let keys = SomePublicKey::from_pem_bundle(bundle.as_bytes(), false)?;
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].as_ecdsa().is_some());
+        assert!(keys[1].as_ed25519().is_some());
+        assert!(keys[2].as_rsa().is_some());
+
+        let with_private = format!("{}{}", bundle, ec.private_key_to_pem_pkcs8()?);
+        assert!(SomePublicKey::from_pem_bundle(with_private.as_bytes(), false).is_err());
+        assert_eq!(
+            SomePublicKey::from_pem_bundle(with_private.as_bytes(), true)?.len(),
+            3
+        );
+
+        let truncated = &bundle[..bundle.len() - 10];
+        assert!(SomePublicKey::from_pem_bundle(truncated.as_bytes(), true).is_err());
+        assert!(SomePublicKey::from_pem_bundle(b"", false)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn debug_redacts_private_keys() -> Result<()> {
+        let k = SomePrivateKey::from(RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?);
+        assert_eq!(
+            format!("{:?}", k),
+            "Rsa(RsaPrivateKey { algorithm: PS256, verify_any: false, allowed_algorithms: [], bits: 2048, private_key: <redacted> })"
+        );
+        let k = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        assert_eq!(
+            format!("{:?}", k),
+            "Ecdsa(EcdsaPrivateKey { algorithm: ES256, low_s: false, deterministic: false, tolerate_short_sig: false, private_key: <redacted> })"
+        );
+        let k = SomePrivateKey::from(Ed25519PrivateKey::generate()?);
+        assert_eq!(
+            format!("{:?}", k),
+            "Ed25519(Ed25519PrivateKey { private_key: <redacted> })"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn can_sign() -> Result<()> {
+        let k = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        assert!(k.can_sign());
+        let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        assert!(!pk.can_sign());
+        let jwk = k.public_key_to_jwk()?;
+        assert!(!jwk.to_verification_key()?.can_sign());
+
+        let k: Box<dyn VerificationKey> = Box::new(crate::jwk::WithKid::new(
+            "k".into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?,
+        ));
+        assert!(k.can_sign());
+        Ok(())
+    }
+
+    #[test]
+    fn describe() -> Result<()> {
+        let k = SomePrivateKey::from(RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?);
+        let info = k.describe();
+        assert_eq!(
+            (info.kty, info.alg, info.bits),
+            ("RSA", Some("PS256"), 2048)
+        );
+        assert_eq!((info.crv, info.e), (None, Some(65537)));
+        let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        assert_eq!(pk.describe().alg, None);
+        assert!(serde_json::to_value(pk.describe())?.get("alg").is_none());
+        assert_eq!(pk.describe().e, Some(65537));
+
+        let k = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES512)?);
+        assert_eq!(
+            serde_json::to_value(k.describe())?,
+            serde_json::json!({"kty": "EC", "alg": "ES512", "bits": 521, "crv": "P-521"})
+        );
+        let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        assert_eq!(pk.describe(), k.describe());
+
+        let k = SomePrivateKey::from(Ed25519PrivateKey::generate()?);
+        assert_eq!(k.describe().crv, Some("Ed25519"));
+        assert_eq!(k.describe().kty, "OKP");
+        Ok(())
+    }
+
+    #[test]
+    fn public_key_pem_format() -> Result<()> {
+        let rsa = SomePrivateKey::from(RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?);
+        let ec = SomePrivateKey::from(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?);
+        let ed = SomePrivateKey::from(Ed25519PrivateKey::generate()?);
+
+        for k in [&rsa, &ec, &ed] {
+            let pem = k.public_key_to_pem_as(PemFormat::Spki)?;
+            assert_eq!(pem, k.public_key_to_pem()?);
+            assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+        }
+
+        let pkcs1 = rsa.public_key_to_pem_as(PemFormat::Pkcs1)?;
+        assert!(pkcs1.starts_with("-----BEGIN RSA PUBLIC KEY-----"));
+        assert!(SomePublicKey::from_pem(pkcs1.as_bytes())?
+            .as_rsa()
+            .is_some());
+        for k in [&ec, &ed] {
+            assert!(matches!(
+                k.public_key_to_pem_as(PemFormat::Pkcs1),
+                Err(Error::UnsupportedOrInvalidKey)
+            ));
+        }
+
+        for k in [&rsa, &ec, &ed] {
+            assert!(matches!(
+                k.public_key_to_pem_as(PemFormat::Sec1),
+                Err(Error::UnsupportedOrInvalidKey)
+            ));
+        }
+        Ok(())
+    }
+}